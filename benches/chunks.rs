@@ -0,0 +1,108 @@
+#![feature(test)]
+extern crate test;
+
+use hashbrown::HashMap;
+use initerse::{chunk::ChunkMap, tiles::{DynMachine, World}, tower::Tower, *};
+use test::{black_box, Bencher};
+
+/// 200×200 machines, 40 000 in total
+const FACTORY_SIZE: i32 = 200;
+/// Roughly what a 1920×1080 screen shows at the base tile size
+const SCREEN_TILES: IVec2 = ivec2(40, 23);
+
+fn factory() -> impl Iterator<Item = (IVec2, DynMachine)> {
+    (-FACTORY_SIZE/2..FACTORY_SIZE/2)
+        .flat_map(|y| (-FACTORY_SIZE/2..FACTORY_SIZE/2).map(move |x| ivec2(x, y)))
+        .map(|coords| (coords, Tower::Electron.new_machine().unwrap()))
+}
+fn flat_map() -> HashMap<IVec2, DynMachine> {
+    factory().collect()
+}
+fn chunk_map() -> ChunkMap {
+    let mut map = ChunkMap::new();
+    for (coords, machine) in factory() {
+        map.insert(coords, machine);
+    }
+    map
+}
+
+/// What `World::update` used to do, a lookup for every tile of the update square
+#[bench]
+fn flat_update_keys(b: &mut Bencher) {
+    let map = flat_map();
+    let half = tiles::BASE_UPDATE_RADIUS as i32/2;
+    b.iter(|| {
+        let mut keys = Vec::new();
+        for x in -half..half {
+            for y in -half..half {
+                let coords = ivec2(x, y);
+                if map.contains_key(&coords) {
+                    keys.push(coords);
+                }
+            }
+        }
+        black_box(keys)
+    });
+}
+#[bench]
+fn chunked_update_keys(b: &mut Bencher) {
+    let map = chunk_map();
+    let half = tiles::BASE_UPDATE_RADIUS as i32/2;
+    b.iter(|| {
+        let keys: Vec<IVec2> = map.chunks_in(IVec2::splat(-half), IVec2::splat(half))
+            .flat_map(|(_, chunk)| chunk.coords())
+            .collect();
+        black_box(keys)
+    });
+}
+
+/// What `WorldView::draw` used to do once there were more machines than screen tiles
+#[bench]
+fn flat_draw_iter(b: &mut Bencher) {
+    let map = flat_map();
+    b.iter(|| {
+        let on_screen = map.keys()
+            .filter(|coords| coords.cmpge(IVec2::ZERO).all() && coords.cmplt(SCREEN_TILES).all())
+            .count();
+        black_box(on_screen)
+    });
+}
+#[bench]
+fn chunked_draw_iter(b: &mut Bencher) {
+    let map = chunk_map();
+    b.iter(|| {
+        let on_screen = map.chunks_in(IVec2::ZERO, SCREEN_TILES)
+            .flat_map(|(_, chunk)| chunk.coords())
+            .filter(|coords| coords.cmpge(IVec2::ZERO).all() && coords.cmplt(SCREEN_TILES).all())
+            .count();
+        black_box(on_screen)
+    });
+}
+
+/// Saving twice without changes only serializes the chunks once
+#[bench]
+fn serialize_unchanged_world(b: &mut Bencher) {
+    let mut world = World::new(1022);
+    for (coords, machine) in factory() {
+        world.set_tower(coords, machine);
+    }
+    world.serialize().unwrap();
+    b.iter(|| black_box(world.saved_machines().unwrap()));
+}
+/// Only the chunks on the two axes changed
+#[bench]
+fn serialize_dirty_world(b: &mut Bencher) {
+    let mut world = World::new(1022);
+    for (coords, machine) in factory() {
+        world.set_tower(coords, machine);
+    }
+    b.iter(|| {
+        for y in -FACTORY_SIZE/2..FACTORY_SIZE/2 {
+            world.try_get_tower_mut(&ivec2(0, y));
+        }
+        for x in -FACTORY_SIZE/2..FACTORY_SIZE/2 {
+            world.try_get_tower_mut(&ivec2(x, 0));
+        }
+        black_box(world.saved_machines().unwrap())
+    });
+}
@@ -1,31 +1,45 @@
 use color_eyre::eyre::ContextCompat as _;
 
+use direction::Direction;
+use render::WorldView;
+
 use super::*;
 pub struct BuildMode {
     pub current: Tower,
+    /// Where the placed machines face
+    pub facing: Direction,
 }
 impl BuildMode {
     pub fn new() -> Self {
         Self {
-            current: Tower::default()
+            current: Tower::default(),
+            facing: Direction::default(),
         }
     }
-    pub async fn draw(&mut self, world: &mut World, player_cell: Vec2, on_hotbar: bool) -> Result<()> {
-        if on_hotbar == true {return Ok(())}
+    pub async fn draw(&mut self, world: &mut World, view: &WorldView, player_cell: Vec2, on_hotbar: bool) -> Result<()> {
+        if on_hotbar {return Ok(())}
         if self.current == Tower::Empty && !is_mouse_button_down(MouseButton::Right)  {return Ok(())}
         if is_mouse_button_released(MouseButton::Right) || is_key_released(KeyCode::Escape) {self.current = Tower::Empty; return Ok(())}
-        let offset = (player_cell.fract_gl()*world.tilesize()).abs();
+        let offset = (player_cell.fract_gl()*view.tilesize()).abs();
         let mut mp = Vec2::from(mouse_position());
         let texture = self.current.loaded_texture().await;
-        let world_cell = world.screen_to_world(mp, player_cell);
+        let world_cell = view.screen_to_world(mp, player_cell);
+        if config::is_action_pressed(config::Action::Rotate) {
+            self.facing = self.facing.rotate_cw();
+        }
         // Check if left click is pressed, if so, build at the current pointed cell
         // If right click is set and we are not in building mode, it means we want to erase some machines
         // We know that self.current = Tower::Empty, so it's like removing the tower
-        if is_mouse_button_down(MouseButton::Left) || (is_mouse_button_down(MouseButton::Right) && self.current == Tower::Empty) {
+        // Machines can't be built over others, they have to be removed first
+        let fits = self.current == Tower::Empty || world.fits(world_cell, self.current);
+        if (is_mouse_button_down(MouseButton::Left) && fits) || (is_mouse_button_down(MouseButton::Right) && self.current == Tower::Empty) {
             let _prev = world.set_tower(world_cell, self.current.new_machine().context("Can't build new machine")?);
+            world.set_facing(&world_cell, self.facing);
         }
-        let scr = world.world_to_screen_offset(world_cell, offset)-(player_cell.floor())*world.tilesize();
-        draw_texture_ex(&texture, scr.x, scr.y, Color::from_rgba(255,255,255,150), DrawTextureParams { dest_size: Some(Vec2::splat(world.tilesize())), ..Default::default() });
+        let scr = view.world_to_screen_offset(world_cell, offset)-(player_cell.floor())*view.tilesize();
+        let tint = if fits {Color::from_rgba(255,255,255,150)} else {Color::from_rgba(255,80,80,150)};
+        let size = self.current.size() as f32*view.tilesize();
+        draw_texture_ex(&texture, scr.x, scr.y, tint, DrawTextureParams { dest_size: Some(Vec2::splat(size)), rotation: self.facing.angle(), ..Default::default() });
         Ok(())
     }
 }
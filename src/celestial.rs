@@ -5,7 +5,7 @@ use toml::Value;
 
 use super::*;
 
-async fn parse_celestial(entry: Result<std::fs::DirEntry, std::io::Error>) -> Result<Celestial> {
+fn parse_celestial(entry: Result<std::fs::DirEntry, std::io::Error>) -> Result<Celestial> {
     let raw = std::fs::read_to_string(entry?.path())?;
     let mut config = toml::de::from_str::<toml::Table>(&raw)?;
     let (x,y) = match config.remove("size").context("No size key in config")? {
@@ -27,9 +27,8 @@ async fn parse_celestial(entry: Result<std::fs::DirEntry, std::io::Error>) -> Re
         }
         _ => return Err(Report::msg("Invalid type for flavor, should be string")),
     };
-    let texture = load_texture(&format!("assets/{}",path)).await?;
     Ok(Celestial {
-        texture,
+        texture_path: path,
         size: ivec2(x as i32, y as i32),
         flavor,
     })
@@ -54,10 +53,17 @@ pub fn parse_asteroid(config: toml::map::Map<String, toml::Value>) -> Result<Ast
     Ok(asteroid)
 }
 
-pub async fn parse_celestials() -> Vec<Celestial> {
+pub fn parse_celestials() -> Vec<Celestial> {
     let mut celestials = Vec::new();
-    for entry in std::fs::read_dir("assets/celestials").unwrap() {
-        celestials.push(match parse_celestial(entry).await {
+    let dir = match std::fs::read_dir("assets/celestials") {
+        Ok(dir) => dir,
+        Err(err) => {warn!("Can't open celestials folder ! {:?}", err);return celestials},
+    };
+    // The directory order depends on the OS, generation needs the same order everywhere
+    let mut entries: Vec<_> = dir.collect();
+    entries.sort_by_key(|entry| entry.as_ref().map(|e| e.file_name()).ok());
+    for entry in entries {
+        celestials.push(match parse_celestial(entry) {
             Ok(file) => file,
             Err(err) => {warn!("Skipping one config entry: {:?}", err);continue},
         })
@@ -67,7 +73,8 @@ pub async fn parse_celestials() -> Vec<Celestial> {
 }
 
 pub struct Celestial {
-    texture: Texture2D,
+    /// Relative to the assets folder
+    texture_path: String,
     pub size: IVec2,
     flavor: CelestialFlavor,
 }
@@ -77,7 +84,7 @@ pub enum CelestialFlavor {
     Asteroid(Asteroid),
 }
 impl Celestial {
-    pub fn texture(&self) -> &Texture2D {&self.texture}
+    pub fn texture_path(&self) -> &str {&self.texture_path}
 }
 //         match self {
 //             Celestial::Star(s) => {
@@ -0,0 +1,171 @@
+use hashbrown::HashMap;
+
+use direction::Direction;
+use tiles::DynMachine;
+
+use super::*;
+
+/// Width and height of a chunk, in tiles
+pub const CHUNK_SIZE: i32 = 16;
+
+/// The coordinates of the chunk containing this tile
+pub fn chunk_of(coords: IVec2) -> IVec2 {
+    coords.div_euclid(IVec2::splat(CHUNK_SIZE))
+}
+
+/// A machine as it sits in the world
+pub struct Placed {
+    pub machine: DynMachine,
+    pub facing: Direction,
+}
+
+/// The machines of a `CHUNK_SIZE`² square of the world
+#[derive(Default)]
+pub struct Chunk {
+    /// By world coordinates, the anchor (top left cell) of bigger machines
+    machines: HashMap<IVec2, Placed>,
+    /// The other cells covered by bigger machines, with the coordinates of their anchor
+    parts: HashMap<IVec2, IVec2>,
+    /// Set when a machine of the chunk changed since the last save
+    dirty: bool,
+    /// Active chunks are updated even when far from the player
+    active: bool,
+}
+impl Chunk {
+    pub fn len(&self) -> usize {self.machines.len()}
+    pub fn is_empty(&self) -> bool {self.machines.is_empty()}
+    pub const fn is_dirty(&self) -> bool {self.dirty}
+    pub const fn is_active(&self) -> bool {self.active}
+    pub fn coords(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.machines.keys().copied()
+    }
+    pub fn machines(&self) -> impl Iterator<Item = (IVec2, &DynMachine)> {
+        self.machines.iter().map(|(coords, placed)| (*coords, &placed.machine))
+    }
+    pub fn placed(&self) -> impl Iterator<Item = (IVec2, &Placed)> {
+        self.machines.iter().map(|(coords, placed)| (*coords, placed))
+    }
+}
+
+/// Every machine of the world, grouped in chunks so only the ones near the player need to be touched
+#[derive(Default)]
+pub struct ChunkMap {
+    chunks: HashMap<IVec2, Chunk>,
+    len: usize,
+}
+impl ChunkMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// How many machines there are
+    pub fn len(&self) -> usize {self.len}
+    pub fn is_empty(&self) -> bool {self.len == 0}
+    pub fn get(&self, coords: &IVec2) -> Option<&DynMachine> {
+        self.get_placed(coords).map(|placed| &placed.machine)
+    }
+    /// The cells covered by bigger machines give the machine at their anchor
+    pub fn get_placed(&self, coords: &IVec2) -> Option<&Placed> {
+        let anchor = self.anchor_of(coords)?;
+        self.chunks.get(&chunk_of(anchor))?.machines.get(&anchor)
+    }
+    /// Marks the chunk as dirty, as the machine might be modified
+    pub fn get_mut(&mut self, coords: &IVec2) -> Option<&mut DynMachine> {
+        let anchor = self.anchor_of(coords)?;
+        let chunk = self.chunks.get_mut(&chunk_of(anchor))?;
+        let placed = chunk.machines.get_mut(&anchor)?;
+        chunk.dirty = true;
+        Some(&mut placed.machine)
+    }
+    /// The anchor of the machine covering this cell, itself for machines of a single cell
+    pub fn anchor_of(&self, coords: &IVec2) -> Option<IVec2> {
+        let chunk = self.chunks.get(&chunk_of(*coords))?;
+        if chunk.machines.contains_key(coords) {
+            return Some(*coords)
+        }
+        chunk.parts.get(coords).copied()
+    }
+    /// Marks a cell as covered by the machine at `anchor`
+    pub fn insert_part(&mut self, coords: IVec2, anchor: IVec2) {
+        self.chunks.entry(chunk_of(coords)).or_default().parts.insert(coords, anchor);
+    }
+    pub fn remove_part(&mut self, coords: &IVec2) -> Option<IVec2> {
+        self.chunks.get_mut(&chunk_of(*coords))?.parts.remove(coords)
+    }
+    pub fn facing(&self, coords: &IVec2) -> Option<Direction> {
+        self.get_placed(coords).map(|placed| placed.facing)
+    }
+    /// Does nothing if there is no machine there
+    pub fn set_facing(&mut self, coords: &IVec2, facing: Direction) {
+        let Some(anchor) = self.anchor_of(coords) else {return};
+        let Some(chunk) = self.chunks.get_mut(&chunk_of(anchor)) else {return};
+        let Some(placed) = chunk.machines.get_mut(&anchor) else {return};
+        placed.facing = facing;
+        chunk.dirty = true;
+    }
+    pub fn contains_key(&self, coords: &IVec2) -> bool {
+        self.get(coords).is_some()
+    }
+    /// Faces the default direction, see [`ChunkMap::insert_placed`]
+    pub fn insert(&mut self, coords: IVec2, machine: DynMachine) -> Option<DynMachine> {
+        self.insert_placed(coords, Placed { machine, facing: Direction::default() }).map(|prev| prev.machine)
+    }
+    /// Only touches the anchor, see [`ChunkMap::insert_part`] for the other cells
+    pub fn insert_placed(&mut self, coords: IVec2, placed: Placed) -> Option<Placed> {
+        let chunk = self.chunks.entry(chunk_of(coords)).or_default();
+        chunk.dirty = true;
+        let prev = chunk.machines.insert(coords, placed);
+        if prev.is_none() {
+            self.len += 1;
+        }
+        prev
+    }
+    pub fn remove(&mut self, coords: &IVec2) -> Option<DynMachine> {
+        self.remove_placed(coords).map(|placed| placed.machine)
+    }
+    /// Only touches the anchor, the cells covered by the machine still point to it.
+    /// Empty chunks are kept, so their removal is still saved
+    pub fn remove_placed(&mut self, coords: &IVec2) -> Option<Placed> {
+        let chunk = self.chunks.get_mut(&chunk_of(*coords))?;
+        let prev = chunk.machines.remove(coords)?;
+        chunk.dirty = true;
+        self.len -= 1;
+        Some(prev)
+    }
+    /// Takes a machine out while it updates, leaving the dirty flag alone. Has to be given back with [`ChunkMap::put_back`]
+    pub fn take(&mut self, coords: &IVec2) -> Option<Placed> {
+        self.chunks.get_mut(&chunk_of(*coords))?.machines.remove(coords)
+    }
+    /// Gives back a machine from [`ChunkMap::take`], `changed` marks its chunk as dirty
+    pub fn put_back(&mut self, coords: IVec2, placed: Placed, changed: bool) {
+        let chunk = self.chunks.entry(chunk_of(coords)).or_default();
+        chunk.dirty |= changed;
+        chunk.machines.insert(coords, placed);
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, &DynMachine)> {
+        self.chunks.values().flat_map(Chunk::machines)
+    }
+    pub fn chunk(&self, chunk: IVec2) -> Option<&Chunk> {
+        self.chunks.get(&chunk)
+    }
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec2, &Chunk)> {
+        self.chunks.iter().map(|(coords, chunk)| (*coords, chunk))
+    }
+    /// The existing chunks overlapping the rectangle of tiles from `min` to `max` (included)
+    pub fn chunks_in(&self, min: IVec2, max: IVec2) -> impl Iterator<Item = (IVec2, &Chunk)> {
+        let (min, max) = (chunk_of(min), chunk_of(max));
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| ivec2(x, y)))
+            .filter_map(|coords| Some((coords, self.chunks.get(&coords)?)))
+    }
+    pub fn set_active(&mut self, chunk: IVec2, active: bool) {
+        self.chunks.entry(chunk).or_default().active = active;
+    }
+    pub fn active_chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.chunks.iter().filter(|(_, chunk)| chunk.active).map(|(coords, _)| *coords)
+    }
+    /// Clears the dirty flag of a chunk, once it has been saved
+    pub fn mark_clean(&mut self, chunk: IVec2) {
+        if let Some(chunk) = self.chunks.get_mut(&chunk) {
+            chunk.dirty = false;
+        }
+    }
+}
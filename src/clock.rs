@@ -0,0 +1,48 @@
+use super::*;
+
+pub const TICKS_PER_SECOND: u32 = 60;
+/// The time simulated by a single tick, in seconds
+pub const TICK_DT: f32 = 1./TICKS_PER_SECOND as f32;
+/// After this many ticks in a single frame the simulation stops catching up, and slows down instead
+pub const MAX_TICKS_PER_FRAME: u32 = 10;
+
+/// Turns frame times into a fixed amount of simulation ticks, so the simulation doesn't depend on the FPS
+pub struct SimClock {
+    /// Time that hasn't been simulated yet, always less than a tick after `advance`
+    accumulator: f64,
+    tick: u64,
+    max_ticks_per_frame: u32,
+}
+impl SimClock {
+    pub fn new() -> Self {
+        Self {
+            accumulator: 0.,
+            tick: 0,
+            max_ticks_per_frame: MAX_TICKS_PER_FRAME,
+        }
+    }
+    pub fn with_max_ticks_per_frame(mut self, max: u32) -> Self {
+        self.max_ticks_per_frame = max;
+        self
+    }
+    /// Adds the time of the last frame, returns how many ticks should be simulated this frame
+    pub fn advance(&mut self, frame_dt: f32) -> u32 {
+        self.accumulator += frame_dt as f64;
+        let mut ticks = (self.accumulator / TICK_DT as f64) as u32;
+        if ticks > self.max_ticks_per_frame {
+            // We are too far behind (e.g. a hitch), drop the time we can't catch up on
+            ticks = self.max_ticks_per_frame;
+            self.accumulator = 0.;
+        } else {
+            self.accumulator -= ticks as f64 * TICK_DT as f64;
+        }
+        self.tick += ticks as u64;
+        ticks
+    }
+    /// How far we are between the last tick and the next one (0 to 1), used to interpolate drawing
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK_DT as f64) as f32
+    }
+    /// How many ticks have been simulated since the clock was created
+    pub const fn tick(&self) -> u64 {self.tick}
+}
@@ -4,6 +4,7 @@ use super::*;
 use color_eyre::eyre::ContextCompat;
 use hashbrown::HashMap;
 use serde::{de::Visitor, ser::SerializeStruct, Serialize};
+use simulation::{SimulationMode, DEFAULT_MAX_OFFLINE_TIME, DEFAULT_SIMULATION_BUDGET, MAX_OFFLINE_TIMES, SIMULATION_BUDGETS};
 use strum::IntoEnumIterator;
 
 pub static mut CONFIG: OnceCell<Config> = OnceCell::new();
@@ -143,6 +144,7 @@ pub enum Action {
     Backward,
     Left,
     Right,
+    Rotate,
 }
 impl Action {
     pub fn default_keycode(self) -> KeyCode {
@@ -151,41 +153,85 @@ impl Action {
             Action::Backward => KeyCode::S,
             Action::Left => KeyCode::Q,
             Action::Right => KeyCode::D,
+            Action::Rotate => KeyCode::R,
         }
     }
 }
+/// Whether the key bound to the action was just pressed
+pub fn is_action_pressed(action: Action) -> bool {
+    let key = unsafe { CONFIG.get() }.and_then(|config| config.keymap.get(&action).copied()).unwrap_or(action.default_keycode());
+    is_key_pressed(key)
+}
+
+pub const DEFAULT_AUTOSAVE_INTERVAL: f32 = 300.;
+/// The intervals the options menu cycles through, 0 disables autosaving
+pub const AUTOSAVE_INTERVALS: [f32; 4] = [60., 300., 600., 0.];
+pub const DEFAULT_BACKUP_COUNT: usize = 5;
+pub const MAX_BACKUP_COUNT: usize = 10;
 
 pub type KeyMap = HashMap<Action, KeyCode>;
 #[derive(Debug)]
 pub struct Config {
     pub keymap: KeyMap,
+    /// Seconds between autosaves, 0 disables it
+    pub autosave_interval: f32,
+    /// How many previous saves are kept for each world
+    pub backup_count: usize,
+    pub simulation_mode: SimulationMode,
+    /// How many machines are updated each tick, see [`simulation::Simulation::budget`]
+    pub simulation_budget: usize,
+    /// How long the game catches up on when loading a world, in seconds, 0 disables it
+    pub max_offline_time: f64,
 }
 impl Config {
+    /// Parses lines that aren't keybinds, returns None if the line isn't a setting
+    fn parse_setting_line(&mut self, line: &str) -> Option<Result<()>> {
+        let (key, value) = line.split_once(" = ")?;
+        Some(match key {
+            "autosave_interval" => value.parse().map(|v| self.autosave_interval = v).map_err(Report::from),
+            "backup_count" => value.parse().map(|v| self.backup_count = v).map_err(Report::from),
+            "simulation_mode" => value.parse().map(|v| self.simulation_mode = v).map_err(Report::from),
+            "simulation_budget" => value.parse().map(|v| self.simulation_budget = v).map_err(Report::from),
+            "max_offline_time" => value.parse().map(|v| self.max_offline_time = v).map_err(Report::from),
+            _ => return None,
+        })
+    }
     fn parse_keybind_line(line: &str) -> Result<(Action, KeyCode)> {
         let mut l = line.split(" = ");
         let action = l.next().context("Need an action and keybind (e.g. Forward = \"Z\")")?.parse()?;
         let raw_bind = l.next().context("Need an action and keybind (e.g. Forward = \"Z\")")?.replace("\"", "");
         // Little cheat I found 🤣
-        let bind = unsafe { core::mem::transmute(raw_bind.parse::<DeserializeKeyCode>()?) };
+        let bind = unsafe { core::mem::transmute::<DeserializeKeyCode, KeyCode>(raw_bind.parse::<DeserializeKeyCode>()?) };
         Ok((action, bind))
     }
     fn read() -> Result<Self> {
         let raw = std::fs::read_to_string("config.toml")?;
-        let mut keymap = KeyMap::default();
+        let mut slf = Self {
+            keymap: KeyMap::default(),
+            autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            simulation_mode: SimulationMode::default(),
+            simulation_budget: DEFAULT_SIMULATION_BUDGET,
+            max_offline_time: DEFAULT_MAX_OFFLINE_TIME,
+        };
         for l in raw.split("\n") {
             let l = l.trim();
             if l.is_empty() {continue}
+            if let Some(res) = slf.parse_setting_line(l) {
+                if let Err(e) = res {
+                    miniquad::warn!("Couldn't parse setting: {:?}", e);
+                }
+                continue
+            }
             match Self::parse_keybind_line(l) {
-                Ok((action, bind)) => {keymap.insert(action, bind);},
+                Ok((action, bind)) => {slf.keymap.insert(action, bind);},
                 Err(e) => {miniquad::warn!("Couldn't parse keybind: {:?}", e);},
             } 
         }
         for action in Action::iter() {
-            keymap.entry(action).or_insert(action.default_keycode());
+            slf.keymap.entry(action).or_insert(action.default_keycode());
         }
-        Ok(Self {
-            keymap,
-        })
+        Ok(slf)
     }
     pub fn get() -> Self {
         if let Ok(slf) = Self::read() {
@@ -201,6 +247,11 @@ impl Config {
                 }
                 map
             },
+            autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            simulation_mode: SimulationMode::default(),
+            simulation_budget: DEFAULT_SIMULATION_BUDGET,
+            max_offline_time: DEFAULT_MAX_OFFLINE_TIME,
         }
     } 
     pub fn write(&self) -> Result<()> {
@@ -208,6 +259,11 @@ impl Config {
         for (action, bind) in self.keymap.iter() {
             raw.push_str(&format!("{action:?} = \"{bind:?}\"\n"));
         }
+        raw.push_str(&format!("autosave_interval = {}\n", self.autosave_interval));
+        raw.push_str(&format!("backup_count = {}\n", self.backup_count));
+        raw.push_str(&format!("simulation_mode = {}\n", self.simulation_mode));
+        raw.push_str(&format!("simulation_budget = {}\n", self.simulation_budget));
+        raw.push_str(&format!("max_offline_time = {}\n", self.max_offline_time));
         std::fs::write("config.toml", raw)?;
         Ok(())
     }
@@ -268,7 +324,31 @@ impl ConfigMenu {
                 return false; // If clicked on this button, won't click on other buttons
             }
         }
-        if button(Rect::new(screen_width()/2.0-100., screen_height()/2.0+100., 200., 50.), "Back", 32., DARKGRAY) {
+        let settings_y = screen_height()/2.0-200.+50.*self.config.keymap.len() as f32;
+        let autosave = if self.config.autosave_interval > 0. {format!("{:.0} min", self.config.autosave_interval/60.)} else {"Off".to_string()};
+        if button(Rect::new(screen_width()/2.0-100., settings_y, 200., 50.), &format!("Autosave: {}", autosave), 32., DARKGRAY) {
+            let current = AUTOSAVE_INTERVALS.iter().position(|i| *i == self.config.autosave_interval);
+            self.config.autosave_interval = AUTOSAVE_INTERVALS[current.map_or(0, |i| (i+1) % AUTOSAVE_INTERVALS.len())];
+        }
+        if button(Rect::new(screen_width()/2.0-100., settings_y+50., 200., 50.), &format!("Backups: {}", self.config.backup_count), 32., DARKGRAY) {
+            self.config.backup_count = (self.config.backup_count+1) % (MAX_BACKUP_COUNT+1);
+        }
+        if button(Rect::new(screen_width()/2.0-100., settings_y+100., 200., 50.), &format!("Simulate: {}", self.config.simulation_mode), 32., DARKGRAY) {
+            self.config.simulation_mode = match self.config.simulation_mode {
+                SimulationMode::Nearby => SimulationMode::Factory,
+                SimulationMode::Factory => SimulationMode::Nearby,
+            };
+        }
+        if self.config.simulation_mode == SimulationMode::Factory && button(Rect::new(screen_width()/2.0-100., settings_y+150., 200., 50.), &format!("Budget: {}", self.config.simulation_budget), 32., DARKGRAY) {
+            let current = SIMULATION_BUDGETS.iter().position(|b| *b == self.config.simulation_budget);
+            self.config.simulation_budget = SIMULATION_BUDGETS[current.map_or(0, |i| (i+1) % SIMULATION_BUDGETS.len())];
+        }
+        let offline = if self.config.max_offline_time > 0. {format!("{:.0} h", self.config.max_offline_time/3600.)} else {"Off".to_string()};
+        if button(Rect::new(screen_width()/2.0-100., settings_y+200., 200., 50.), &format!("Offline: {}", offline), 32., DARKGRAY) {
+            let current = MAX_OFFLINE_TIMES.iter().position(|t| *t == self.config.max_offline_time);
+            self.config.max_offline_time = MAX_OFFLINE_TIMES[current.map_or(0, |i| (i+1) % MAX_OFFLINE_TIMES.len())];
+        }
+        if button(Rect::new(screen_width()/2.0-100., settings_y+250., 200., 50.), "Back", 32., DARKGRAY) {
             if let Err(e) = self.config.write() {
                 miniquad::warn!("Error saving config ! {e:?}");
             }
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::*;
+
+/// One of the 4 sides of a tile, `Up` is towards negative y like on screen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, strum_macros::EnumIter, strum_macros::EnumString)]
+pub enum Direction {
+    Up,
+    #[default]
+    Right,
+    Down,
+    Left,
+}
+impl Direction {
+    /// The offset to the adjacent tile on this side
+    pub const fn offset(self) -> IVec2 {
+        match self {
+            Direction::Up => IVec2::NEG_Y,
+            Direction::Right => IVec2::X,
+            Direction::Down => IVec2::Y,
+            Direction::Left => IVec2::NEG_X,
+        }
+    }
+    pub const fn opposite(self) -> Self {
+        self.rotate_cw().rotate_cw()
+    }
+    pub const fn rotate_cw(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+    /// The direction of a side of a machine facing this way
+    pub const fn turned(self, side: Side) -> Self {
+        match side {
+            Side::Front => self,
+            Side::Right => self.rotate_cw(),
+            Side::Back => self.opposite(),
+            Side::Left => self.opposite().rotate_cw(),
+        }
+    }
+    /// Which side of a machine facing this way is in the given direction
+    pub fn side_of(self, direction: Direction) -> Side {
+        Side::iter().find(|side| self.turned(*side) == direction).unwrap()
+    }
+    /// The clockwise rotation from `Right`, in radians, for drawing
+    pub fn angle(self) -> f32 {
+        match self {
+            Direction::Up => -std::f32::consts::FRAC_PI_2,
+            Direction::Right => 0.,
+            Direction::Down => std::f32::consts::FRAC_PI_2,
+            Direction::Left => std::f32::consts::PI,
+        }
+    }
+}
+
+/// A side of a machine, relative to where it faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum_macros::EnumIter, strum_macros::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Side {
+    Front,
+    Right,
+    Back,
+    Left,
+}
+impl Side {
+    /// Parses the ports of a tower, e.g. "back,left,right", "all" being every side
+    pub fn parse_list(list: &str) -> impl Iterator<Item = Side> + '_ {
+        list.split(',').flat_map(|side| match side.trim() {
+            "all" => Side::iter().collect::<Vec<_>>(),
+            side => side.parse().ok().into_iter().collect(),
+        })
+    }
+}
@@ -0,0 +1,152 @@
+use entity::{Behaviour, Body, EntityCtx, EntityType};
+use item::{Item, ItemStack};
+use serde::{Deserialize, Serialize};
+use tiles::Map;
+
+use super::*;
+
+/// Tiles per second at full power, without modules
+pub const DRONE_SPEED: f32 = 4.;
+/// Items carried at once, without modules
+pub const DRONE_CAPACITY: u32 = 5;
+/// Energy per second drawn by the home tower while its drone works, without modules
+pub const DRONE_POWER: f32 = 2.;
+/// How close a drone has to be to a target to reach it, in tiles
+const ARRIVED: f32 = 0.05;
+
+/// Upgrades installed in a drone's home tower
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumIter)]
+pub enum DroneModule {
+    /// Only picks up the item chosen in the home tower
+    Filter,
+    /// Flies 50% faster, but uses 50% more energy
+    Speed,
+    /// Carries 5 more items
+    Capacity,
+    /// Uses 30% less energy
+    Efficiency,
+}
+
+/// What a drone's home tower tells it to do, see [`tower::Machine::drone_config`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DroneConfig {
+    pub pickup: Option<IVec2>,
+    pub dropoff: Option<IVec2>,
+    /// Tiles per second, 0 when the home tower has no power
+    pub speed: f32,
+    pub capacity: u32,
+    /// Only this item is picked up when set
+    pub filter: Option<Item>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DroneState {
+    /// Going to the pickup to fill up
+    #[default]
+    Pickup,
+    /// Going to the dropoff to empty its cargo
+    Dropoff,
+}
+
+/// Carries items between two machines, flying over everything.
+/// It lives as long as its home tower, see [`tower::drone_hub`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Drone {
+    home: [i32; 2],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cargo: Option<ItemStack>,
+    #[serde(default)]
+    state: DroneState,
+}
+impl Drone {
+    pub fn new(home: IVec2) -> Self {
+        Self { home: home.into(), cargo: None, state: DroneState::default() }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn home(&self) -> IVec2 {self.home.into()}
+    pub fn cargo(&self) -> Option<ItemStack> {self.cargo}
+    pub fn state(&self) -> DroneState {self.state}
+    /// Where the drone hovers to reach the machine covering `coords`, the middle of the machine
+    pub fn landing(map: &Map, coords: IVec2) -> Vec2 {
+        let anchor = map.anchor_of(&coords).unwrap_or(coords);
+        let size = map.get(&anchor).map_or(1, |machine| machine.ty().size());
+        vec2i_to_f(anchor)+Vec2::splat(size as f32/2.)
+    }
+    /// Heads towards `target` without overshooting it, returns whether it is there
+    fn fly_to(body: &mut Body, target: Vec2, speed: f32, dt: f32) -> bool {
+        let to = target-body.pos;
+        if to.length() <= ARRIVED {
+            body.vel = Vec2::ZERO;
+            return true
+        }
+        body.vel = to.normalize()*speed.min(to.length()/dt);
+        false
+    }
+    /// Takes what it can carry, or puts its cargo down, at the machine it reached
+    fn work(&mut self, map: &mut Map, target: IVec2, config: DroneConfig) {
+        let Some(machine) = map.get_mut(&target) else {return};
+        match self.state {
+            DroneState::Pickup => {
+                let carried = self.cargo.map_or(0, |cargo| cargo.amount);
+                let wanted = machine.outputs().into_iter().find(|stack| {
+                    config.filter.is_none_or(|filter| filter == stack.item) && self.cargo.is_none_or(|cargo| cargo.item == stack.item)
+                });
+                if let Some(stack) = wanted {
+                    let taken = machine.take(stack.item, config.capacity.saturating_sub(carried));
+                    if taken > 0 {
+                        self.cargo = Some(ItemStack::new(stack.item, carried+taken));
+                    }
+                }
+                // Leaves with whatever it got, or keeps waiting for something to show up
+                if self.cargo.is_some() {
+                    self.state = DroneState::Dropoff;
+                }
+            },
+            DroneState::Dropoff => {
+                if let Some(cargo) = self.cargo {
+                    let leftover = machine.insert(cargo.item, cargo.amount);
+                    self.cargo = (leftover > 0).then_some(ItemStack::new(cargo.item, leftover));
+                }
+                if self.cargo.is_none() {
+                    self.state = DroneState::Pickup;
+                }
+            },
+        }
+    }
+}
+impl Behaviour for Drone {
+    fn update(&mut self, body: &mut Body, ctx: &mut EntityCtx, dt: f32) -> Result<bool> {
+        let map = &mut *ctx.map;
+        let Some(config) = map.get(&self.home()).and_then(|home| home.drone_config()) else {return Ok(false)};
+        let target = match self.state {
+            DroneState::Pickup => config.pickup,
+            DroneState::Dropoff => config.dropoff,
+        };
+        // Waits at home until it has somewhere to go
+        let Some(target) = target.filter(|target| map.contains_key(target)) else {
+            Self::fly_to(body, Self::landing(map, self.home()), config.speed, dt);
+            return Ok(true)
+        };
+        if Self::fly_to(body, Self::landing(map, target), config.speed, dt) {
+            self.work(map, target, config);
+        }
+        Ok(true)
+    }
+    fn draw(&self, body: &Body, pos: Vec2, tilesize: f32) {
+        let radius = tilesize*0.2;
+        draw_circle(pos.x, pos.y, radius, Color::from_rgba(220,220,230,255));
+        draw_circle_lines(pos.x, pos.y, radius*1.6, 2., Color::from_rgba(220,220,230,120));
+        if let Some(cargo) = self.cargo {
+            let size = radius;
+            draw_rectangle(pos.x-size/2., pos.y-size/2., size, size, cargo.item.color());
+        }
+    }
+    fn ty(&self) -> EntityType {
+        EntityType::Drone
+    }
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+}
@@ -0,0 +1,208 @@
+//! Shifters, monsters made of antimatter coming in waves to annihilate with the base
+use entity::{Behaviour, Body, EntityCtx, EntityType};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use tiles::Map;
+use tower::WorldCommand;
+
+use super::*;
+
+/// Tiles per second
+pub const SHIFTER_SPEED: f32 = 1.5;
+/// Every machine this close to a shifter goes away with it, in tiles
+pub const ANNIHILATION_RADIUS: f32 = 1.5;
+/// How often shifters look for the nearest structure again, in seconds
+const RETARGET_INTERVAL: f32 = 1.;
+/// How far past the furthest machine waves appear, in tiles
+pub const SPAWN_DISTANCE: f32 = 20.;
+/// Waves never come more often than this, in seconds
+pub const MIN_WAVE_INTERVAL: f32 = 20.;
+/// Each this much energy per second produced makes waves come twice as often, and adds a shifter
+pub const PRODUCTION_PER_STEP: f32 = 50.;
+/// Waves never get bigger than this
+pub const MAX_WAVE_SIZE: u32 = 30;
+
+/// Chosen when creating a world
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumIter, strum_macros::Display, strum_macros::EnumString, strum_macros::EnumProperty)]
+pub enum Difficulty {
+    /// No shifters at all, the default for worlds from before shifters
+    #[default]
+    #[strum(props(interval = "0", size = "0"))]
+    Peaceful,
+    #[strum(props(interval = "300", size = "1"))]
+    Easy,
+    #[strum(props(interval = "180", size = "2"))]
+    Normal,
+    #[strum(props(interval = "90", size = "4"))]
+    Hard,
+}
+impl Difficulty {
+    fn prop(self, name: &str) -> f32 {
+        strum::EnumProperty::get_str(&self, name).and_then(|value| value.parse().ok()).unwrap_or(0.)
+    }
+    /// The one after it, back to the first one after the last one
+    pub fn next(self) -> Self {
+        Self::iter().skip_while(|d| *d != self).nth(1).unwrap_or_default()
+    }
+    /// Seconds between waves without any energy production, None if there are no waves
+    pub fn base_interval(self) -> Option<f32> {
+        let interval = self.prop("interval");
+        (interval > 0.).then_some(interval)
+    }
+    /// Shifters per wave without any energy production
+    pub fn base_size(self) -> u32 {
+        self.prop("size") as u32
+    }
+    /// Seconds until the next wave when the base produces `production` energy per second
+    pub fn wave_interval(self, production: f32) -> Option<f32> {
+        Some((self.base_interval()?/(1.+production.max(0.)/PRODUCTION_PER_STEP)).max(MIN_WAVE_INTERVAL))
+    }
+    pub fn wave_size(self, production: f32) -> u32 {
+        (self.base_size()+(production.max(0.)/PRODUCTION_PER_STEP) as u32).min(MAX_WAVE_SIZE)
+    }
+}
+
+/// When shifters come, saved with the world
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waves {
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Seconds until the next wave
+    #[serde(default)]
+    pub until_next: f32,
+    /// Waves sent so far
+    #[serde(default)]
+    pub count: u32,
+}
+impl Default for Waves {
+    fn default() -> Self {
+        Self::new(Difficulty::default())
+    }
+}
+impl Waves {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self { difficulty, until_next: difficulty.base_interval().unwrap_or(0.), count: 0 }
+    }
+    /// Counts down to the next wave, returns how many shifters it brings when it comes
+    pub fn update(&mut self, production: f32, dt: f32) -> Option<u32> {
+        let interval = self.difficulty.wave_interval(production)?;
+        // A growing base gets the next wave sooner
+        self.until_next = self.until_next.min(interval)-dt;
+        if self.until_next > 0. {return None}
+        self.until_next += interval;
+        self.count += 1;
+        Some(self.difficulty.wave_size(production))
+    }
+}
+
+/// Where waves come from: on a circle around the origin, just past the furthest machine
+pub fn spawn_radius(map: &Map) -> f32 {
+    let explored = map.iter().map(|(coords, _)| (vec2i_to_f(coords)+Vec2::splat(0.5)).length()).fold(0., f32::max);
+    explored+SPAWN_DISTANCE
+}
+
+/// Made of antimatter, goes around other machines to the nearest structure and annihilates with it
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shifter {
+    /// The anchor of the machine it goes to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<[i32; 2]>,
+    /// Seconds until it looks for the nearest structure again
+    #[serde(default)]
+    retarget_in: f32,
+    /// The cells left to go through to the target, the next one last. Found again after loading
+    #[serde(skip)]
+    path: Vec<IVec2>,
+}
+impl Shifter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn target(&self) -> Option<IVec2> {self.target.map(IVec2::from)}
+    /// The anchor of the machine with its center closest to `pos`
+    fn nearest(map: &Map, pos: Vec2) -> Option<IVec2> {
+        map.iter()
+            .map(|(coords, machine)| (coords, center_of(coords, machine.ty().size()).distance_squared(pos)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(coords, _)| coords)
+    }
+    /// Removes every machine around the shifter, along with the shifter
+    fn annihilate(pos: Vec2, ctx: &mut EntityCtx) {
+        let map = &*ctx.map;
+        let range = ANNIHILATION_RADIUS.ceil() as i32;
+        let cell = vec2i(pos.floor());
+        let mut hit = Vec::new();
+        for y in -range..=range {
+            for x in -range..=range {
+                let Some(anchor) = map.anchor_of(&(cell+ivec2(x, y))) else {continue};
+                let size = map.get(&anchor).map_or(1, |machine| machine.ty().size());
+                if !hit.contains(&anchor) && distance_to_machine(pos, anchor, size) <= ANNIHILATION_RADIUS {
+                    hit.push(anchor);
+                }
+            }
+        }
+        for anchor in hit {
+            ctx.command(WorldCommand::SetTower { coords: anchor, machine: Tower::Empty.new_machine().unwrap() });
+        }
+    }
+}
+fn center_of(anchor: IVec2, size: i32) -> Vec2 {
+    vec2i_to_f(anchor)+Vec2::splat(size as f32/2.)
+}
+/// How far `pos` is from the closest point of a machine, 0 when it is over it
+fn distance_to_machine(pos: Vec2, anchor: IVec2, size: i32) -> f32 {
+    let min = vec2i_to_f(anchor);
+    pos.distance(pos.clamp(min, min+Vec2::splat(size as f32)))
+}
+impl Behaviour for Shifter {
+    fn update(&mut self, body: &mut Body, ctx: &mut EntityCtx, dt: f32) -> Result<bool> {
+        // Touching any machine is enough to blow up, whatever it was going for
+        if ctx.map.anchor_of(&body.cell()).is_some() {
+            Self::annihilate(body.pos, ctx);
+            return Ok(false)
+        }
+        self.retarget_in -= dt;
+        let target_gone = self.target().is_none_or(|target| !ctx.map.contains_key(&target));
+        // A machine built on the way blocks it
+        let blocked = self.path.last().is_some_and(|next| ctx.map.anchor_of(next).is_some_and(|anchor| Some(anchor) != self.target()));
+        if self.retarget_in <= 0. || target_gone || blocked {
+            self.target = Self::nearest(ctx.map, body.pos).map(Into::into);
+            self.retarget_in = RETARGET_INTERVAL;
+            self.path = match self.target() {
+                Some(target) => ctx.paths.find(ctx.map, body.cell(), target).map_or_else(Vec::new, |path| path.into_iter().rev().collect()),
+                None => Vec::new(),
+            };
+        }
+        // Nothing left to destroy, it drifts where it is
+        let Some(target) = self.target() else {
+            body.vel = Vec2::ZERO;
+            return Ok(true)
+        };
+        while self.path.last().is_some_and(|next| body.pos.distance(vec2i_to_f(*next)+Vec2::splat(0.5)) < 0.05) {
+            self.path.pop();
+        }
+        // Straight at the target when it is walled in
+        let size = ctx.map.get(&target).map_or(1, |machine| machine.ty().size());
+        let goal = self.path.last().map_or(center_of(target, size), |next| vec2i_to_f(*next)+Vec2::splat(0.5));
+        let to = goal-body.pos;
+        // Doesn't fly past the machine during long updates
+        body.vel = to.normalize_or_zero()*SHIFTER_SPEED.min(to.length()/dt);
+        Ok(true)
+    }
+    fn draw(&self, body: &Body, pos: Vec2, tilesize: f32) {
+        let pulse = (get_time() as f32*4.+body.pos.x).sin()*0.05;
+        let radius = tilesize*(0.3+pulse);
+        draw_circle(pos.x, pos.y, radius*1.4, Color::from_rgba(192,57,43,60));
+        draw_circle(pos.x, pos.y, radius, Color::from_hex(0xc0392b));
+        draw_circle(pos.x, pos.y, radius*0.5, Color::from_rgba(20,0,0,255));
+    }
+    fn ty(&self) -> EntityType {
+        EntityType::Shifter
+    }
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+}
@@ -0,0 +1,183 @@
+use hashbrown::HashMap;
+
+use tiles::Map;
+use tower::NEIGHBOURS;
+
+use super::*;
+
+/// How a machine takes part in a power grid, see [`tower::Machine::energy`].
+/// Rates are in energy per second, a tick uses `rate*dt` of them
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EnergyPort {
+    pub supply: f32,
+    pub demand: f32,
+    pub stored: f32,
+    pub capacity: f32,
+    /// How far the machine reaches other machines of the grid, 1 only joins the adjacent ones
+    pub range: i32,
+}
+impl EnergyPort {
+    pub fn generator(supply: f32) -> Self {
+        Self { supply, range: 1, ..Default::default() }
+    }
+    pub fn consumer(demand: f32) -> Self {
+        Self { demand, range: 1, ..Default::default() }
+    }
+    pub fn battery(stored: f32, capacity: f32) -> Self {
+        Self { stored, capacity, range: 1, ..Default::default() }
+    }
+    pub fn connector(range: i32) -> Self {
+        Self { range, ..Default::default() }
+    }
+}
+
+/// The state of a grid during the last tick
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GridStats {
+    /// Energy per second
+    pub production: f32,
+    /// Energy per second
+    pub demand: f32,
+    pub stored: f32,
+    pub capacity: f32,
+    /// The fraction of the demand that was met, from 0 to 1
+    pub satisfaction: f32,
+    pub machines: usize,
+}
+impl GridStats {
+    /// Draws the stats in a machine's GUI, returns the height it took
+    pub fn draw(&self, x: f32, y: f32) -> f32 {
+        let lines = [
+            format!("Grid of {} machines", self.machines),
+            format!("Production: {:.1}/s - Demand: {:.1}/s", self.production, self.demand),
+            format!("Stored: {:.0}/{:.0}", self.stored, self.capacity),
+            format!("Satisfaction: {:.0}%", self.satisfaction*100.),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == 3 && self.satisfaction < 1. {ORANGE} else {WHITE};
+            draw_text(line, x, y+24.*i as f32, 24., color);
+        }
+        24.*lines.len() as f32
+    }
+}
+
+struct Grid {
+    members: Vec<IVec2>,
+    stats: GridStats,
+}
+
+/// Every power grid of the world, rebuilt when machines are placed or removed
+pub struct EnergyNetwork {
+    grids: Vec<Grid>,
+    /// Index in `grids` of every machine using energy
+    grid_of: HashMap<IVec2, usize>,
+    dirty: bool,
+}
+impl EnergyNetwork {
+    pub fn new() -> Self {
+        Self { grids: Vec::new(), grid_of: HashMap::new(), dirty: true }
+    }
+    /// The grids need to be rebuilt before the next tick
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+    pub fn grid_count(&self) -> usize {self.grids.len()}
+    /// Energy per second produced by every grid, as of the last tick
+    pub fn production(&self) -> f32 {
+        self.grids.iter().map(|grid| grid.stats.production).sum()
+    }
+    pub fn stats(&self, coords: IVec2) -> Option<GridStats> {
+        Some(self.grids[*self.grid_of.get(&coords)?].stats)
+    }
+    /// Joins the machines using energy that reach each other into grids
+    fn rebuild(&mut self, map: &Map) {
+        let nodes: Vec<(IVec2, i32, i32)> = map.iter()
+            .filter_map(|(coords, machine)| Some((coords, machine.energy()?.range, machine.ty().size())))
+            .collect();
+        let index: HashMap<IVec2, usize> = nodes.iter().enumerate().map(|(i, (coords, _, _))| (*coords, i)).collect();
+        let mut parents: Vec<usize> = (0..nodes.len()).collect();
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        for (i, (coords, range, size)) in nodes.iter().enumerate() {
+            // A connection only needs to be found from one side.
+            // Bigger machines reach from every cell they cover
+            let (min, max) = (*coords, *coords+IVec2::splat(size-1));
+            let reached: Vec<IVec2> = if *range <= 1 {
+                (min.y..=max.y).flat_map(|y| (min.x..=max.x).map(move |x| ivec2(x, y)))
+                    .flat_map(|cell| NEIGHBOURS.iter().map(move |offset| cell+*offset))
+                    .collect()
+            } else {
+                (min.y-range..=max.y+range).flat_map(|y| (min.x-range..=max.x+range).map(move |x| ivec2(x, y))).collect()
+            };
+            for other in reached {
+                let Some(j) = map.anchor_of(&other).and_then(|anchor| index.get(&anchor)) else {continue};
+                let (a, b) = (root(&mut parents, i), root(&mut parents, *j));
+                parents[a] = b;
+            }
+        }
+        let mut grids_by_root: HashMap<usize, usize> = HashMap::new();
+        self.grids.clear();
+        self.grid_of.clear();
+        for (i, (coords, _, _)) in nodes.iter().enumerate() {
+            let r = root(&mut parents, i);
+            let grid = *grids_by_root.entry(r).or_insert_with(|| {
+                self.grids.push(Grid { members: Vec::new(), stats: GridStats::default() });
+                self.grids.len()-1
+            });
+            self.grids[grid].members.push(*coords);
+            self.grid_of.insert(*coords, grid);
+        }
+        self.dirty = false;
+    }
+    /// Shares the energy of each grid for a tick: consumers are throttled on shortage, batteries store the surplus
+    pub fn solve(&mut self, map: &mut Map, dt: f32) {
+        if self.dirty {
+            self.rebuild(map);
+        }
+        for grid in &mut self.grids {
+            let mut stats = GridStats { machines: grid.members.len(), ..Default::default() };
+            for coords in &grid.members {
+                let Some(port) = map.get(coords).and_then(|machine| machine.energy()) else {continue};
+                stats.production += port.supply;
+                stats.demand += port.demand;
+                stats.stored += port.stored;
+                stats.capacity += port.capacity;
+            }
+            let (produced, needed) = (stats.production*dt, stats.demand*dt);
+            // Positive charges the batteries, negative drains them
+            let battery_change = if produced >= needed {
+                stats.satisfaction = 1.;
+                (produced-needed).min(stats.capacity-stats.stored)
+            } else {
+                let drained = (needed-produced).min(stats.stored);
+                stats.satisfaction = (produced+drained)/needed;
+                -drained
+            };
+            for coords in &grid.members {
+                let Some(port) = map.get(coords).and_then(|machine| machine.energy()) else {continue};
+                // Only touch machines the grid changes, so the others' chunks aren't saved again
+                if port.demand <= 0. && (port.capacity <= 0. || battery_change == 0.) {continue}
+                let Some(machine) = map.get_mut(coords) else {continue};
+                if port.demand > 0. {
+                    machine.set_power(stats.satisfaction);
+                }
+                if port.capacity > 0. && battery_change != 0. {
+                    // Each battery takes its share of the free space, or gives its share of the stored energy
+                    let share = if battery_change > 0. {
+                        (port.capacity-port.stored)/(stats.capacity-stats.stored)
+                    } else {
+                        port.stored/stats.stored
+                    };
+                    machine.charge(battery_change*share);
+                }
+            }
+            stats.stored += battery_change;
+            grid.stats = stats;
+        }
+    }
+}
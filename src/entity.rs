@@ -0,0 +1,184 @@
+//! Things moving freely over the map, unlike machines they aren't bound to tiles
+use std::collections::BTreeMap;
+
+use hashbrown::HashMap;
+use pathfinding::Pathfinder;
+use serde::{Deserialize, Serialize};
+use tiles::Map;
+use tower::WorldCommand;
+
+use super::*;
+
+/// Never reused, so entities can keep track of each other across ticks and saves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumIter, strum_macros::EnumString)]
+pub enum EntityType {
+    Drone,
+    Shifter,
+}
+impl EntityType {
+    /// Rebuilds an entity from the state returned by [`Behaviour::serialize`]
+    pub fn deserialize_behaviour(self, state: toml::Table) -> Result<Box<dyn Behaviour>> {
+        Ok(match self {
+            EntityType::Drone => Box::new(drone::Drone::deserialize(state)?),
+            EntityType::Shifter => Box::new(enemy::Shifter::deserialize(state)?),
+        })
+    }
+}
+
+/// Where an entity is and where it goes, in tiles. The center of the tile (0, 0) is at (0.5, 0.5)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Body {
+    pub pos: Vec2,
+    /// Tiles per second, applied by [`Entities::update`] after the entity updated
+    pub vel: Vec2,
+}
+impl Body {
+    pub fn at(pos: Vec2) -> Self {
+        Self { pos, vel: Vec2::ZERO }
+    }
+    /// The tile the entity is over
+    pub fn cell(&self) -> IVec2 {
+        vec2i(self.pos.floor())
+    }
+}
+
+/// What an entity sees of the world while it updates
+pub struct EntityCtx<'a> {
+    /// The entity being updated
+    pub id: EntityId,
+    /// Entities can use machines directly, e.g. drones taking items
+    pub map: &'a mut Map,
+    /// Shared by every entity, so paths found by one are reused by the others
+    pub paths: &'a mut Pathfinder,
+    commands: &'a mut Vec<WorldCommand>,
+}
+impl<'a> EntityCtx<'a> {
+    pub fn new(id: EntityId, map: &'a mut Map, paths: &'a mut Pathfinder, commands: &'a mut Vec<WorldCommand>) -> Self {
+        Self { id, map, paths, commands }
+    }
+    /// Changes applied to the world along with the ones of the machines
+    pub fn command(&mut self, command: WorldCommand) {
+        self.commands.push(command);
+    }
+}
+
+/// The part of an entity specific to its type, like [`tower::Machine`] for machines
+pub trait Behaviour {
+    /// Returns false once the entity should be removed
+    fn update(&mut self, body: &mut Body, ctx: &mut EntityCtx, dt: f32) -> Result<bool>;
+    /// `pos` is the center of the entity on screen
+    fn draw(&self, body: &Body, pos: Vec2, tilesize: f32);
+    fn ty(&self) -> EntityType;
+    /// The state needed to rebuild the entity with [`EntityType::deserialize_behaviour`]
+    fn serialize(&self) -> Result<toml::Table>;
+}
+
+pub struct Entity {
+    id: EntityId,
+    pub body: Body,
+    behaviour: Box<dyn Behaviour>,
+}
+impl Entity {
+    pub fn new(id: EntityId, body: Body, behaviour: Box<dyn Behaviour>) -> Self {
+        Self { id, body, behaviour }
+    }
+    pub fn id(&self) -> EntityId {self.id}
+    pub fn ty(&self) -> EntityType {self.behaviour.ty()}
+    pub fn behaviour(&self) -> &dyn Behaviour {&*self.behaviour}
+    pub fn behaviour_mut(&mut self) -> &mut dyn Behaviour {&mut *self.behaviour}
+    pub fn draw(&self, pos: Vec2, tilesize: f32) {
+        self.behaviour.draw(&self.body, pos, tilesize);
+    }
+}
+
+/// Every entity of a world, with an index by chunk for spatial queries
+#[derive(Default)]
+pub struct Entities {
+    next_id: u64,
+    /// Sorted by id, so entities always update in the same order
+    entities: BTreeMap<EntityId, Entity>,
+    /// The entities over each chunk, kept up to date after every change
+    buckets: HashMap<IVec2, Vec<EntityId>>,
+}
+impl Entities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {self.entities.len()}
+    pub fn is_empty(&self) -> bool {self.entities.is_empty()}
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values()
+    }
+    pub fn of_type(&self, ty: EntityType) -> impl Iterator<Item = &Entity> {
+        self.iter().filter(move |entity| entity.ty() == ty)
+    }
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+    /// Moving the entity doesn't update the spatial index until the next tick
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+    pub fn spawn(&mut self, pos: Vec2, behaviour: Box<dyn Behaviour>) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.insert(Entity::new(id, Body::at(pos), behaviour));
+        id
+    }
+    /// Adds an entity keeping its id, e.g. when loading a save
+    pub fn insert(&mut self, entity: Entity) {
+        self.next_id = self.next_id.max(entity.id.0+1);
+        self.buckets.entry(bucket_of(entity.body.pos)).or_default().push(entity.id);
+        self.entities.insert(entity.id, entity);
+    }
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        if let Some(bucket) = self.buckets.get_mut(&bucket_of(entity.body.pos)) {
+            bucket.retain(|other| *other != id);
+        }
+        Some(entity)
+    }
+    /// Updates then moves every entity, removing the ones that are done
+    pub fn update(&mut self, map: &mut Map, paths: &mut Pathfinder, commands: &mut Vec<WorldCommand>, dt: f32) -> Result<()> {
+        let ids: Vec<EntityId> = self.entities.keys().copied().collect();
+        for id in ids {
+            let Some(entity) = self.entities.get_mut(&id) else {continue};
+            let alive = entity.behaviour.update(&mut entity.body, &mut EntityCtx::new(id, map, paths, commands), dt)
+                .with_context(|| format!("Can't update {:?} {:?}", entity.ty(), id))?;
+            if alive {
+                entity.body.pos += entity.body.vel*dt;
+            } else {
+                self.entities.remove(&id);
+            }
+        }
+        self.reindex();
+        Ok(())
+    }
+    fn reindex(&mut self) {
+        self.buckets.clear();
+        for (id, entity) in &self.entities {
+            self.buckets.entry(bucket_of(entity.body.pos)).or_default().push(*id);
+        }
+    }
+    /// The entities over the chunks overlapping this rect of tiles
+    fn candidates(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = &Entity> {
+        let (min, max) = (bucket_of(min), bucket_of(max));
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| ivec2(x, y)))
+            .filter_map(|bucket| self.buckets.get(&bucket))
+            .flatten()
+            .filter_map(|id| self.entities.get(id))
+    }
+    /// The entities at most `radius` tiles away from `center`
+    pub fn within_radius(&self, center: Vec2, radius: f32) -> impl Iterator<Item = &Entity> {
+        self.candidates(center-radius, center+radius).filter(move |entity| entity.body.pos.distance(center) <= radius)
+    }
+    /// The entities inside a rect of tiles
+    pub fn in_rect(&self, rect: Rect) -> impl Iterator<Item = &Entity> {
+        self.candidates(rect.point(), rect.point()+rect.size()).filter(move |entity| rect.contains(entity.body.pos))
+    }
+}
+fn bucket_of(pos: Vec2) -> IVec2 {
+    chunk::chunk_of(vec2i(pos.floor()))
+}
@@ -40,7 +40,12 @@ impl TextBox {
         }
         if self.focused {
             while let Some(char) = get_char_pressed() {
-                self.text.push(char);
+                if !char.is_control() {
+                    self.text.push(char);
+                }
+            }
+            if is_key_pressed(KeyCode::Backspace) {
+                self.text.pop();
             }
             self.rect.w = self.text.len() as f32*12.;
         }
@@ -3,35 +3,26 @@ use strum::{EnumProperty, IntoEnumIterator};
 
 use super::*;
 
+/// The hotbar has at least this many slots, more when there are more buildable towers
 pub const SLOTS: usize = 10;
 
 pub type Slot = Tower;
 
 pub struct Hotbar {
-    slots: [Slot; SLOTS],
+    slots: Vec<Slot>,
 }
 impl Hotbar {
     pub fn new() -> Self {
-        Self {
-            slots: {
-                let mut slots = [Tower::Empty; SLOTS];
-                let mut si = 0;
-                for (i, tower) in Tower::iter().enumerate() {
-                    if tower.get_str("buildable") == Some("true") {
-                        slots[si] = tower;
-                        si += 1;
-                    }
-                }
-                slots
-            },
-        }
+        let mut slots: Vec<Slot> = tower::registry::buildable().collect();
+        slots.resize(slots.len().max(SLOTS), Tower::Empty);
+        Self { slots }
     }
     pub async fn draw(&mut self, build_mode: &mut BuildMode) -> Result<bool> {
         let x = 100.;
         let w = screen_width()-x*2.;
         let h = 50.;
         let y = screen_height()-h;
-        let slot_size = vec2(w/SLOTS as f32, h);
+        let slot_size = vec2(w/self.slots.len() as f32, h);
         draw_rectangle(x, y, w, h, GRAY);
         for (i, slot) in self.slots.iter().enumerate() {
             if *slot == Tower::Empty {continue;}
@@ -43,7 +34,7 @@ impl Hotbar {
         if on_hotbar {
             let slot_x = (mx-x) / slot_size.x;
             if is_mouse_button_released(MouseButton::Left) {
-                let idx = slot_x as usize;
+                let idx = (slot_x as usize).min(self.slots.len()-1);
                 build_mode.current = self.slots[idx];
             }
         }
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use strum::EnumProperty;
+
+use super::*;
+
+pub const DEFAULT_STACK_SIZE: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, strum_macros::EnumIter, strum_macros::EnumCount, strum_macros::EnumProperty, strum_macros::EnumString)]
+pub enum Item {
+    #[strum(props(stack_size = "1000", color = "fff3a0"))]
+    Photon,
+    #[strum(props(stack_size = "500", color = "9b59b6"))]
+    String,
+    #[strum(props(color = "e74c3c"))]
+    Quark,
+    #[strum(props(color = "3fa9f5"))]
+    Electron,
+    #[strum(props(color = "e67e22"))]
+    Proton,
+    #[strum(props(color = "bdc3c7"))]
+    Neutron,
+    #[strum(props(color = "f39c12"))]
+    Nucleus,
+    #[strum(props(color = "1abc9c"))]
+    Atom,
+    #[strum(props(stack_size = "10", color = "c0392b"))]
+    Antimatter,
+    #[strum(props(color = "7f8c8d"))]
+    Iron,
+    #[strum(props(color = "f1c40f"))]
+    Sulfur,
+    #[strum(props(color = "d0d3d4"))]
+    Titanium,
+}
+impl Item {
+    /// How many items of this type fit in a single slot
+    pub fn stack_size(self) -> u32 {
+        self.get_str("stack_size").and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_STACK_SIZE)
+    }
+    /// The color of the item when drawn in the world, e.g. on conveyors
+    pub fn color(self) -> Color {
+        self.get_str("color").and_then(|hex| u32::from_str_radix(hex, 16).ok()).map_or(WHITE, Color::from_hex)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: Item,
+    pub amount: u32,
+}
+impl ItemStack {
+    pub fn new(item: Item, amount: u32) -> Self {
+        Self { item, amount }
+    }
+}
+
+/// A single inventory slot, optionally restricted to one item type
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Slot {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Item>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<ItemStack>,
+}
+impl Slot {
+    pub fn accepts(&self, item: Item) -> bool {
+        self.filter.is_none_or(|f| f == item) && self.stack.is_none_or(|s| s.item == item)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Slot>,
+    /// Overrides the stack size of every item if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stack_limit: Option<u32>,
+}
+impl Inventory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![Slot::default(); capacity],
+            stack_limit: None,
+        }
+    }
+    /// An inventory with one slot per filter, each only accepting its item
+    pub fn with_filters(filters: &[Item]) -> Self {
+        Self {
+            slots: filters.iter().map(|f| Slot { filter: Some(*f), stack: None }).collect(),
+            stack_limit: None,
+        }
+    }
+    pub fn with_stack_limit(mut self, limit: u32) -> Self {
+        self.stack_limit = Some(limit);
+        self
+    }
+    pub fn capacity(&self) -> usize {self.slots.len()}
+    pub fn slots(&self) -> &[Slot] {&self.slots}
+    pub fn stack_limit(&self, item: Item) -> u32 {
+        self.stack_limit.unwrap_or(item.stack_size())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|s| s.stack.is_none())
+    }
+    pub fn count(&self, item: Item) -> u32 {
+        self.slots.iter().filter_map(|s| s.stack).filter(|s| s.item == item).map(|s| s.amount).sum()
+    }
+    /// How many items of this type can still be inserted
+    pub fn space_for(&self, item: Item) -> u32 {
+        let limit = self.stack_limit(item);
+        self.slots.iter().filter(|s| s.accepts(item)).map(|s| limit.saturating_sub(s.stack.map_or(0, |s| s.amount))).sum()
+    }
+    /// Inserts as many items as possible, filling existing stacks first, returns how many didn't fit
+    pub fn insert(&mut self, item: Item, mut amount: u32) -> u32 {
+        let limit = self.stack_limit(item);
+        for slot in self.slots.iter_mut().filter(|s| s.accepts(item) && s.stack.is_some()) {
+            let stack = slot.stack.as_mut().unwrap();
+            let added = amount.min(limit.saturating_sub(stack.amount));
+            stack.amount += added;
+            amount -= added;
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.accepts(item) && s.stack.is_none()) {
+            if amount == 0 {break}
+            let added = amount.min(limit);
+            slot.stack = Some(ItemStack::new(item, added));
+            amount -= added;
+        }
+        amount
+    }
+    /// Takes up to `amount` items out of the inventory, returns how many were taken
+    pub fn take(&mut self, item: Item, amount: u32) -> u32 {
+        let mut taken = 0;
+        for slot in self.slots.iter_mut().rev() {
+            if taken == amount {break}
+            let Some(stack) = slot.stack.as_mut() else {continue};
+            if stack.item != item {continue}
+            let n = stack.amount.min(amount - taken);
+            stack.amount -= n;
+            taken += n;
+            if stack.amount == 0 {
+                slot.stack = None;
+            }
+        }
+        taken
+    }
+    /// Takes exactly `amount` items, or nothing if there aren't enough. Returns whether they were taken
+    pub fn take_exact(&mut self, item: Item, amount: u32) -> bool {
+        if self.count(item) < amount {return false}
+        self.take(item, amount);
+        true
+    }
+    /// Adds empty slots
+    pub fn grow(&mut self, slots: usize) {
+        self.slots.extend(std::iter::repeat_n(Slot::default(), slots));
+    }
+    /// Every item type in the inventory with its total count
+    pub fn contents(&self) -> Vec<ItemStack> {
+        let mut contents: Vec<ItemStack> = Vec::new();
+        for stack in self.slots.iter().filter_map(|s| s.stack) {
+            match contents.iter_mut().find(|s| s.item == stack.item) {
+                Some(s) => s.amount += stack.amount,
+                None => contents.push(stack),
+            }
+        }
+        contents
+    }
+}
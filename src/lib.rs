@@ -1,19 +1,19 @@
 // #![cfg_attr(debug_assertions, allow(unused))]
 // #![cfg_attr(debug_assertions, warn(unused_results))]
 #![allow(unused_mut, dead_code, unused_variables, unused_imports)]
-// Very usefull to initialise empty arrays
-// + It's experimental because they are not 100% okay about semantics
-#![feature(inline_const_pat)]
-#![feature(get_mut_unchecked)]
+// The game state lives in a few globals (WORLD, CONFIG) that are only touched from the main thread
+#![allow(static_mut_refs)]
+#![allow(clippy::new_without_default)]
 // #![warn(clippy::unused_async)]
 
-use std::{fs::read_to_string, path::PathBuf, sync::{Arc, Mutex}};
+use std::{fs::read_to_string, sync::{Arc, Mutex}};
 
 use config::Config;
 pub use macroquad::prelude::*;
 pub use color_eyre::{Result,Report};
+use color_eyre::eyre::Context;
 use miniquad::window::order_quit;
-use tiles::{World, EMPTY_MACHINE};
+use tiles::World;
 
 pub mod tiles;
 pub mod camera;
@@ -24,14 +24,29 @@ pub mod tower;
 pub mod celestial;
 pub mod gui;
 pub mod config;
+pub mod item;
+pub mod clock;
+pub mod render;
+pub mod save;
+pub mod chunk;
+pub mod simulation;
+pub mod energy;
+pub mod direction;
+pub mod recipe;
+pub mod drone;
+pub mod entity;
+pub mod enemy;
+pub mod pathfinding;
 
 use tower::{EmptyMachine, Tower};
 use gui::*;
 
 
 pub async fn _main() -> Result<()> {
+    tower::registry::load_towers().context("Can't load the towers")?;
     tower::setup_cache_tower_textures().await?;
     unsafe { config::CONFIG.set(Config::get()).unwrap() }
+    recipe::load_recipes().context("Can't load the recipes")?;
     loop {
         if button(Rect::new(screen_width()/2.0-100., screen_height()/2.0-100., 200., 50.), "New world", 32., DARKGRAY) {
             new_world_scene().await?;
@@ -51,51 +66,178 @@ pub async fn _main() -> Result<()> {
 }
 async fn new_world_scene() -> Result<()> {
     let seed = format!("{}", ::rand::random::<u64>());
+    let mut name_inp = TextBox::new("New world".to_string(), Rect::new(screen_width()/2.0-100., screen_height()/2.0-200., 200., 50.), DARKBLUE);
     let mut seed_inp = TextBox::new(seed, Rect::new(screen_width()/2.0-100., screen_height()/2.0-100., 200., 50.), DARKBLUE);
+    let mut difficulty = enemy::Difficulty::Normal;
     loop {
+        name_inp.update();
         seed_inp.update();
         if button(Rect::new(screen_width()/2.0-100., screen_height()/2.0+100., 200., 50.), "Play", 32., DARKGRAY) {
-            let mut seed_n = 0;
-            for b in seed_inp.text.bytes() {
-                seed_n += b as u64;
-            }
-            println!("Generating world with seed: {}", seed_n);
-            rand::srand(seed_n);
-            let mut world = World::new(seed_n).await;
+            let seed_n = hash_seed(&seed_inp.text);
+            println!("Generating world with seed: {} ({})", seed_inp.text, seed_n);
+            let mut world = World::new(seed_n);
+            world.meta_mut().name = name_inp.text.clone();
+            world.set_difficulty(difficulty);
             world.set_tower(ivec2(-1, -1), Tower::Electron.new_machine().unwrap());
             // world.set_tower(ivec2(0, 0), Tower::StringCreator.new_machine().unwrap());
             world.set_tower(ivec2(1, 1), Tower::Electron.new_machine().unwrap());
             return game_loop(world).await
         }
+        draw_text("Name", name_inp.rect.x, name_inp.rect.y-8., 24., WHITE);
+        draw_text("Seed", seed_inp.rect.x, seed_inp.rect.y-8., 24., WHITE);
+        draw_text("Shifters", seed_inp.rect.x, screen_height()/2.0-8., 24., WHITE);
+        if button(Rect::new(screen_width()/2.0-100., screen_height()/2.0, 200., 50.), &difficulty.to_string(), 32., DARKGRAY) {
+            difficulty = difficulty.next();
+        }
+        name_inp.draw();
         seed_inp.draw();
 
         next_frame().await;
     }
 }
-fn get_saves() -> Vec<PathBuf> {
-    let dir = match std::fs::read_dir("saves") {
-        Ok(dir) => dir,
+/// A world of the saves folder, as shown on the load screen
+struct SaveEntry {
+    name: String,
+    meta: Result<save::meta::WorldMeta>,
+    thumbnail: Option<Texture2D>,
+}
+fn get_saves() -> Vec<SaveEntry> {
+    let dir = save::files::SaveDir::default();
+    let saves = match dir.saves() {
+        Ok(saves) => saves,
         Err(e) => {
             miniquad::warn!("Can't open saves folder ! {:?}", e);
             return vec![]
         },
     };
-    let mut saves = vec![]; // Vec::with_capacity(dir.count())
-    for save in dir {
-        if let Ok(save) = save {
-            saves.push(save.path())
-        }
+    let mut entries = Vec::with_capacity(saves.len());
+    for path in saves {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let meta = dir.read_meta(&name);
+        let thumbnail = meta.as_ref().ok().and_then(|meta| meta.thumbnail.as_ref()).map(|thumbnail| {
+            let size = save::meta::THUMBNAIL_SIZE as u16;
+            let texture = Texture2D::from_rgba8(size, size, &thumbnail.rgba());
+            texture.set_filter(FilterMode::Nearest);
+            texture
+        });
+        entries.push(SaveEntry { name, meta, thumbnail });
     }
-    saves
+    // Most recently played first
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.meta.as_ref().map_or(0, |meta| meta.last_played)));
+    entries
 }
 async fn load_world_scene() -> Result<()> {
-    let saves = get_saves();
+    let dir = save::files::SaveDir::default();
+    let mut saves = get_saves();
+    let mut renaming: Option<(usize, TextBox)> = None;
+    let mut deleting: Option<usize> = None;
     loop {
+        let mut changed = false;
         for (i,save) in saves.iter().enumerate() {
-            if button(Rect::new(screen_width()/2.0-100., screen_height()/2.0-200.+75.*i as f32, 200., 50.), &format!("{:?}", save), 32., DARKGRAY) {
-                let mut world = World::load(read_to_string(save)?).await?;
+            let (x, y) = (40., 40.+110.*i as f32);
+            if let Some(thumbnail) = &save.thumbnail {
+                draw_texture_ex(thumbnail, x, y, WHITE, DrawTextureParams { dest_size: Some(vec2(64., 64.)), ..Default::default() });
+            } else {
+                draw_rectangle(x, y, 64., 64., BLACK);
+            }
+            match &save.meta {
+                Ok(meta) => {
+                    draw_text(&meta.name, x+80., y+24., 32., WHITE);
+                    let details = format!("{} - played {} - last played {}", save.name, save::meta::format_duration(meta.play_time), save::meta::format_date(meta.last_played));
+                    draw_text(&details, x+80., y+44., 20., LIGHTGRAY);
+                },
+                Err(e) => {
+                    draw_text(&save.name, x+80., y+24., 32., WHITE);
+                    draw_text(&format!("Corrupt save: {}", e), x+80., y+44., 20., RED);
+                },
+            }
+            let action = |n: usize, label: &str| button(Rect::new(x+80.+100.*n as f32, y+54., 90., 30.), label, 24., DARKGRAY);
+            if save.meta.is_ok() && action(0, "Play") {
+                let mut world = dir.load(&save.name)?;
+                let max_offline = unsafe { config::CONFIG.get().unwrap() }.max_offline_time;
+                let report = world.offline_progress(save::meta::now(), max_offline)?;
+                if !report.is_empty() {
+                    offline_report_scene(&report).await?;
+                }
                 return game_loop(world).await
             }
+            if action(1, "Rename") {
+                let current = save.meta.as_ref().map_or(save.name.clone(), |meta| meta.name.clone());
+                renaming = Some((i, TextBox::new(current, Rect::new(x+80., y, 200., 30.), DARKBLUE)));
+            }
+            if save.meta.is_ok() && action(2, "Copy") {
+                dir.duplicate(&save.name)?;
+                changed = true;
+            }
+            if action(3, if deleting == Some(i) {"Sure ?"} else {"Delete"}) {
+                if deleting == Some(i) {
+                    dir.delete(&save.name)?;
+                    deleting = None;
+                    changed = true;
+                } else {
+                    deleting = Some(i);
+                }
+            }
+            if action(4, "Backups") {
+                return restore_backup_scene(&save.name).await
+            }
+        }
+        if let Some((i, name_inp)) = &mut renaming {
+            name_inp.update();
+            name_inp.draw();
+            let ok = Rect::new(name_inp.rect.x+name_inp.rect.w+10., name_inp.rect.y, 60., 30.);
+            if button(ok, "OK", 24., DARKGRAY) || is_key_pressed(KeyCode::Enter) {
+                dir.rename(&saves[*i].name, &name_inp.text)?;
+                renaming = None;
+                changed = true;
+            }
+        }
+        if changed {
+            saves = get_saves();
+        }
+        if button(Rect::new(screen_width()/2.0-100., screen_height()-100., 200., 50.), "Back", 32., DARKGRAY) {
+            return Ok(())
+        }
+
+        next_frame().await;
+    }
+}
+/// Shows what the machines did while the game was closed
+async fn offline_report_scene(report: &simulation::OfflineReport) -> Result<()> {
+    loop {
+        let x = screen_width()/2.0-200.;
+        draw_text(&format!("You were away for {}", save::meta::format_duration(report.away)), x, 100., 32., WHITE);
+        if report.simulated < report.away {
+            draw_text(&format!("Only the first {} were simulated", save::meta::format_duration(report.simulated)), x, 130., 24., LIGHTGRAY);
+        }
+        if report.produced.is_empty() {
+            draw_text("Nothing was produced", x, 180., 24., WHITE);
+        }
+        for (i, stack) in report.produced.iter().enumerate() {
+            draw_text(&format!("{:?}: +{}", stack.item, stack.amount), x, 180.+28.*i as f32, 24., WHITE);
+        }
+        if button(Rect::new(screen_width()/2.0-100., screen_height()-100., 200., 50.), "Continue", 32., DARKGRAY) || is_key_pressed(KeyCode::Enter) {
+            return Ok(())
+        }
+
+        next_frame().await;
+    }
+}
+async fn restore_backup_scene(name: &str) -> Result<()> {
+    let dir = save::files::SaveDir::default();
+    let backups = dir.backups(name);
+    loop {
+        draw_text(&format!("Backups of {}, newest first", name), screen_width()/2.0-200., 100., 32., WHITE);
+        for (i, backup) in backups.iter().enumerate() {
+            if button(Rect::new(screen_width()/2.0-100., screen_height()/2.0-200.+75.*i as f32, 200., 50.), &format!("Restore #{}", i+1), 32., DARKGRAY) {
+                let backup_count = unsafe { config::CONFIG.get().unwrap() }.backup_count;
+                dir.restore_backup(name, backup, backup_count)?;
+                let mut world = dir.load(name)?;
+                return game_loop(world).await
+            }
+        }
+        if button(Rect::new(screen_width()/2.0-100., screen_height()-100., 200., 50.), "Back", 32., DARKGRAY) {
+            return Ok(())
         }
 
         next_frame().await;
@@ -135,6 +277,9 @@ async fn game_loop(world: World) -> Result<()> {
     let mut hotbar = hotbar::Hotbar::new();
     let mut player = player::new();
     let mut build_mode = build_mode::BuildMode::new();
+    let mut view = render::WorldView::new(world).await?;
+    let mut clock = clock::SimClock::new();
+    let mut autosave = save::files::Autosave::new();
     loop {
         if is_quit_requested() {
             unsafe { config::CONFIG.get().unwrap().write().unwrap() }
@@ -147,16 +292,34 @@ async fn game_loop(world: World) -> Result<()> {
             game_options_scene().await?;
         }
 
-        player.update(dt);
-        world.update(player.pos, dt)?;
-        
-        world.draw(player.pos).await?;
+        let config = unsafe { config::CONFIG.get().unwrap() };
+        world.simulation_mut().mode = config.simulation_mode;
+        world.simulation_mut().budget = config.simulation_budget;
+        for _ in 0..clock.advance(dt) {
+            player.update(clock::TICK_DT);
+            world.update(player.pos, clock::TICK_DT)?;
+        }
+        let player_pos = player.interpolated_pos(clock.alpha());
+        world.meta_mut().play_time += dt as f64;
+        if autosave.update(dt, config.autosave_interval) {
+            if let Err(e) = world.save() {
+                miniquad::warn!("Autosave failed ! {:?}", e);
+            }
+        }
+
+        view.draw(world, player_pos).await?;
         let on_hot = hotbar.draw(&mut build_mode).await?;
-        build_mode.draw(&mut world, player.pos, on_hot).await?;
-        world.interact(player.pos, &build_mode)?;
-        world.control_tilesize()?;
+        build_mode.draw(world, &view, player_pos, on_hot).await?;
+        view.interact(world, &mut player, player_pos, &build_mode)?;
+        view.control_tilesize()?;
 
-        draw_text(&format!("X: {:.1} Y: {:.1}\nFPS: {:.1}", player.pos.x,player.pos.y, 1./dt), 20., 20., 32., WHITE);
+        draw_text(&format!("X: {:.1} Y: {:.1}\nFPS: {:.1}", player_pos.x,player_pos.y, 1./dt), 20., 20., 32., WHITE);
+        if world.waves().difficulty.base_interval().is_some() {
+            draw_text(&format!("Shifters in {:.0}s", world.waves().until_next.ceil()), screen_width()-220., 30., 28., Color::from_hex(0xc0392b));
+        }
+        for (i, stack) in player.inventory.contents().iter().enumerate() {
+            draw_text(&format!("{:?}: {}", stack.item, stack.amount), 20., 50.+24.*i as f32, 24., WHITE);
+        }
 
         next_frame().await;
     }
@@ -179,4 +342,46 @@ pub fn rand_with_seed(seed: u64) -> (u32, u64) {
     let xorshifted: u32 = (((seed >> 18) ^ seed) >> 27) as u32;
     let rot: u32 = (seed >> 59) as u32;
     (xorshifted.rotate_right(rot), seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407))
-}
\ No newline at end of file
+}
+
+/// Turns the seed typed by the player into a world seed, stable across versions and platforms (FNV-1a)
+pub fn hash_seed(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in text.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// A random number generator owned by a world, so generation only depends on the seed
+/// and not on whatever else consumed macroquad's global generator
+#[derive(Debug, Clone)]
+pub struct WorldRng {
+    state: u64,
+}
+impl WorldRng {
+    pub fn new(seed: u64) -> Self {
+        let mut slf = Self { state: seed };
+        // The first output only depends on the high bits of the seed, skip it
+        slf.next_u32();
+        slf
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        let (n, state) = rand_with_seed(self.state);
+        self.state = state;
+        n
+    }
+    /// A number between 0 (included) and 1 (excluded)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+    pub fn gen_range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high-low)*self.next_f32()
+    }
+    /// A number between `low` (included) and `high` (excluded), `low` if the range is empty
+    pub fn gen_range_i32(&mut self, low: i32, high: i32) -> i32 {
+        if high <= low {return low}
+        low.wrapping_add((self.next_u32() % high.abs_diff(low)) as i32)
+    }
+}
@@ -0,0 +1,129 @@
+//! Paths over the tile grid, going around machines to reach one of them
+use std::collections::VecDeque;
+
+use hashbrown::HashMap;
+use tiles::Map;
+use tower::NEIGHBOURS;
+
+use super::*;
+
+/// How many cells the search around a target reaches before giving up, keeps unreachable and far targets cheap
+pub const MAX_SEARCHED_CELLS: usize = 8192;
+/// How many targets are remembered, the least recently used one is forgotten past that
+pub const MAX_CACHED_TARGETS: usize = 64;
+
+/// A breadth first search going out from every cell of a machine, continued whenever a path starts further away
+struct Field {
+    /// The anchor of the machine
+    target: IVec2,
+    /// Steps to the closest cell of the target, for every cell reached so far
+    distance: HashMap<IVec2, u32>,
+    /// Cells reached whose neighbours haven't been looked at yet, closest first
+    frontier: VecDeque<IVec2>,
+    /// Corners of the box holding every cell the search looked at, only changes inside it can change the result
+    min: IVec2,
+    max: IVec2,
+    last_used: u64,
+}
+impl Field {
+    fn new(map: &Map, target: IVec2) -> Self {
+        let size = map.get(&target).map_or(1, |machine| machine.ty().size());
+        let cells: Vec<IVec2> = (0..size).flat_map(|y| (0..size).map(move |x| target+ivec2(x, y))).collect();
+        Self {
+            target,
+            distance: cells.iter().map(|cell| (*cell, 0)).collect(),
+            frontier: cells.into(),
+            min: target,
+            max: target+IVec2::splat(size-1),
+            last_used: 0,
+        }
+    }
+    fn covers(&self, cell: IVec2) -> bool {
+        cell.cmpge(self.min).all() && cell.cmple(self.max).all()
+    }
+    /// Searches further until one of `cells` is reached, returns it
+    fn reach(&mut self, map: &Map, cells: &[IVec2]) -> Option<IVec2> {
+        loop {
+            if let Some(cell) = cells.iter().find(|cell| self.distance.contains_key(*cell)) {
+                return Some(*cell)
+            }
+            if self.distance.len() > MAX_SEARCHED_CELLS {return None}
+            let cell = self.frontier.pop_front()?;
+            let next_distance = self.distance[&cell]+1;
+            for offset in NEIGHBOURS {
+                let next = cell+offset;
+                if self.distance.contains_key(&next) {continue}
+                (self.min, self.max) = (self.min.min(next), self.max.max(next));
+                if map.anchor_of(&next).is_some() {continue}
+                self.distance.insert(next, next_distance);
+                self.frontier.push_back(next);
+            }
+        }
+    }
+    /// Goes down the distances from `start`, which has to be reached already
+    fn walk(&self, start: IVec2) -> Vec<IVec2> {
+        let mut path = vec![start];
+        let mut distance = self.distance[&start];
+        while distance > 0 {
+            let previous = *path.last().unwrap();
+            let next = NEIGHBOURS.iter().map(|offset| previous+*offset)
+                .find(|next| self.distance.get(next) == Some(&(distance-1)))
+                .unwrap();
+            path.push(next);
+            distance -= 1;
+        }
+        path
+    }
+}
+
+/// Finds paths to machines, remembering the search around each machine until a machine is placed or removed where it looked.
+/// Everything going to the same machine shares its search, wherever it starts from
+#[derive(Default)]
+pub struct Pathfinder {
+    fields: HashMap<IVec2, Field>,
+    uses: u64,
+}
+impl Pathfinder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// How many targets are remembered
+    pub fn cached_len(&self) -> usize {self.fields.len()}
+    /// `target` is the anchor of the machine
+    pub fn is_cached(&self, target: IVec2) -> bool {
+        self.fields.contains_key(&target)
+    }
+    /// Forgets the searches that could go another way now that this cell changed, see [`tiles::World::set_tower`]
+    pub fn invalidate(&mut self, cell: IVec2) {
+        self.fields.retain(|_, field| !field.covers(cell));
+    }
+    pub fn clear(&mut self) {
+        self.fields.clear();
+    }
+    /// The cells to walk through from `from` to the machine covering `to`, ending on the first cell of the machine reached.
+    /// Every other machine is in the way, `from` itself can be covered by one. None if there is no way there,
+    /// or if `from` is further than [`MAX_SEARCHED_CELLS`] lets the search go, about 60 tiles in the open.
+    /// Shifters go straight at their target then, and find a path once they are close enough
+    pub fn find(&mut self, map: &Map, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+        let target = map.anchor_of(&to)?;
+        if !self.fields.contains_key(&target) && self.fields.len() >= MAX_CACHED_TARGETS {
+            let oldest = self.fields.values().min_by_key(|field| field.last_used).map(|field| field.target)?;
+            self.fields.remove(&oldest);
+        }
+        self.uses += 1;
+        let field = self.fields.entry(target).or_insert_with(|| Field::new(map, target));
+        field.last_used = self.uses;
+        // From inside a machine, the way out is through one of its neighbours
+        let starts = if map.anchor_of(&from).is_some_and(|anchor| anchor != target) {
+            NEIGHBOURS.map(|offset| from+offset).to_vec()
+        } else {
+            vec![from]
+        };
+        let start = field.reach(map, &starts)?;
+        let mut path = field.walk(start);
+        if start == from {
+            path.remove(0);
+        }
+        Some(path)
+    }
+}
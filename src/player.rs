@@ -1,29 +1,37 @@
 use std::{borrow::Borrow, cell::RefCell};
 
 use config::{Action, CONFIG};
+use item::Inventory;
 
 use super::*;
 
 thread_local! {
-    pub static CONTROLS: RefCell<[(Action, Vec2); 4]> = RefCell::new([
+    pub static CONTROLS: RefCell<[(Action, Vec2); 4]> = const { RefCell::new([
         (Action::Forward, Vec2::NEG_Y),
         (Action::Left, Vec2::NEG_X),
         (Action::Backward, Vec2::Y),
         (Action::Right, Vec2::X),
-    ])
+    ]) }
 }
 
+pub const PLAYER_INVENTORY_SLOTS: usize = 20;
+
 pub struct Player {
     pub pos: Vec2,
+    /// The position before the last tick, used to interpolate drawing between ticks
+    pub prev_pos: Vec2,
     /// The current velocity of the player
     pub vel: Vec2,
-    /// How much the player can accelerate in a single frame
+    /// How much the player can accelerate in a second
     pub acceleration: f32,
     pub max_vel: f32,
     pub damping: f32,
+    pub inventory: Inventory,
 }
 impl Player {
+    /// Moves the player by a single simulation tick
     pub fn update(&mut self, dt: f32) {
+        self.prev_pos = self.pos;
         let mut accel = Vec2::ZERO;
         let config = unsafe { CONFIG.get_mut().unwrap() };
         CONTROLS.with_borrow(|keys| {
@@ -38,22 +46,29 @@ impl Player {
         if self.vel.length() > self.max_vel {
             self.vel = self.vel.normalize() * self.max_vel;
         }
-        self.pos += self.vel;
+        self.pos += self.vel*dt;
+        // Damping is applied once per tick, so it only stays consistent on a fixed tick rate
         self.vel *= self.damping;
-        if self.vel.x.abs() <= 0.01 {
+        if self.vel.x.abs() <= 0.6 {
             self.vel.x = 0.;
         }
-        if self.vel.y.abs() <= 0.01 {
+        if self.vel.y.abs() <= 0.6 {
             self.vel.y = 0.;
         }
     }
+    /// Where to draw the player, `alpha` being how far we are between the last tick and the next one
+    pub fn interpolated_pos(&self, alpha: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
 }
 pub fn new() -> Player {
     Player {
         pos: Vec2::splat(0.001), // Simple fix, because we use ceil for tile drawing
+        prev_pos: Vec2::splat(0.001),
         vel: Vec2::ZERO,
-        acceleration: 2.5,
-        max_vel: 10.,
+        acceleration: 150.,
+        max_vel: 600.,
         damping: 0.9,
+        inventory: Inventory::new(PLAYER_INVENTORY_SLOTS),
     }
 }
\ No newline at end of file
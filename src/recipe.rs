@@ -0,0 +1,146 @@
+use std::{path::Path, str::FromStr, sync::OnceLock};
+
+use color_eyre::eyre::{bail, eyre, Context};
+use hashbrown::HashSet;
+use item::{Item, ItemStack};
+use serde::Deserialize;
+use tower::{crafter::CRAFTER_INPUT_SLOTS, Tower};
+
+use super::*;
+
+pub const RECIPES_DIR: &str = "assets/recipes";
+
+/// Every recipe of the game, loaded once from [`RECIPES_DIR`]
+static RECIPES: OnceLock<Vec<Recipe>> = OnceLock::new();
+
+/// Turns inputs into outputs over time, in a crafter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    /// Unique, saves refer to recipes by name
+    pub name: String,
+    /// The crafter that can make it
+    pub machine: Tower,
+    pub inputs: Vec<ItemStack>,
+    pub outputs: Vec<ItemStack>,
+    /// Seconds per craft at full power
+    pub duration: f32,
+    /// Energy used by a single craft
+    pub energy: f32,
+}
+impl Recipe {
+    /// Energy per second while crafting
+    pub fn power(&self) -> f32 {
+        self.energy/self.duration
+    }
+    /// E.g. "3 Quark -> 1 Proton (3s, 30 energy)"
+    pub fn describe(&self) -> String {
+        let list = |stacks: &[ItemStack]| stacks.iter().map(|s| format!("{} {:?}", s.amount, s.item)).collect::<Vec<_>>().join(" + ");
+        format!("{} -> {} ({}s, {} energy)", list(&self.inputs), list(&self.outputs), self.duration, self.energy)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRecipe {
+    name: String,
+    machine: String,
+    #[serde(default)]
+    inputs: toml::Table,
+    outputs: toml::Table,
+    duration: f32,
+    #[serde(default)]
+    energy: f32,
+}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeFile {
+    #[serde(default)]
+    recipe: Vec<RawRecipe>,
+}
+
+fn parse_stacks(table: toml::Table) -> Result<Vec<ItemStack>> {
+    table.into_iter().map(|(item, amount)| {
+        let item = Item::from_str(&item).map_err(|_| eyre!("Unknown item {:?}", item))?;
+        let amount = amount.as_integer().filter(|amount| *amount > 0)
+            .ok_or_else(|| eyre!("The amount of {:?} should be a positive integer, got {}", item, amount))?;
+        Ok(ItemStack::new(item, amount.try_into()?))
+    }).collect()
+}
+fn parse_recipe(raw: RawRecipe) -> Result<Recipe> {
+    let machine = Tower::named(&raw.machine).ok_or_else(|| eyre!("Unknown machine {:?}", raw.machine))?;
+    if !machine.is_crafter() {
+        bail!("{} isn't a crafter", machine.name())
+    }
+    let inputs = parse_stacks(raw.inputs).context("Invalid inputs")?;
+    let outputs = parse_stacks(raw.outputs).context("Invalid outputs")?;
+    if outputs.is_empty() {
+        bail!("No outputs")
+    }
+    if inputs.len() > CRAFTER_INPUT_SLOTS {
+        bail!("Crafters only take {} different inputs, got {}", CRAFTER_INPUT_SLOTS, inputs.len())
+    }
+    if raw.duration <= 0. || !raw.duration.is_finite() {
+        bail!("The duration should be positive, got {}", raw.duration)
+    }
+    if raw.energy < 0. || !raw.energy.is_finite() {
+        bail!("The energy cost can't be negative, got {}", raw.energy)
+    }
+    Ok(Recipe { name: raw.name, machine, inputs, outputs, duration: raw.duration, energy: raw.energy })
+}
+/// Parses the `[[recipe]]` entries of a recipe file
+pub fn parse_recipe_file(raw: &str) -> Result<Vec<Recipe>> {
+    let file: RecipeFile = toml::from_str(raw)?;
+    file.recipe.into_iter().enumerate().map(|(i, raw)| {
+        let name = raw.name.clone();
+        parse_recipe(raw).with_context(|| format!("In recipe {:?} (entry {})", name, i+1))
+    }).collect()
+}
+/// Checks what can only be seen once every file is loaded
+pub fn validate(recipes: &[Recipe]) -> Result<()> {
+    let mut names = HashSet::new();
+    for recipe in recipes {
+        if !names.insert(recipe.name.as_str()) {
+            bail!("Two recipes are named {:?}", recipe.name)
+        }
+    }
+    Ok(())
+}
+/// Loads every `.toml` file in `dir`, fails on the first invalid one
+pub fn parse_recipes_in(dir: impl AsRef<Path>) -> Result<Vec<Recipe>> {
+    let dir = dir.as_ref();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {warn!("Can't open recipes folder ! {:?}", err);return Ok(Vec::new())},
+    };
+    // Same order everywhere, so the recipe selector doesn't change between machines
+    let mut paths: Vec<_> = entries.filter_map(|entry| Some(entry.ok()?.path())).filter(|path| path.extension().is_some_and(|ext| ext == "toml")).collect();
+    paths.sort();
+    let mut recipes = Vec::new();
+    for path in paths {
+        let raw = std::fs::read_to_string(&path).with_context(|| format!("Can't read {}", path.display()))?;
+        recipes.extend(parse_recipe_file(&raw).with_context(|| format!("Invalid recipe file {}", path.display()))?);
+    }
+    validate(&recipes)?;
+    Ok(recipes)
+}
+/// Loads the recipes, called at startup so broken recipe files stop the game with a clear error
+pub fn load_recipes() -> Result<()> {
+    let recipes = parse_recipes_in(RECIPES_DIR)?;
+    // Already loaded if something needed them before
+    let _ = RECIPES.set(recipes);
+    Ok(())
+}
+/// Every recipe, loads them if [`load_recipes`] wasn't called
+pub fn recipes() -> &'static [Recipe] {
+    RECIPES.get_or_init(|| parse_recipes_in(RECIPES_DIR).unwrap_or_else(|err| {
+        warn!("Can't load recipes: {:?}", err);
+        Vec::new()
+    }))
+}
+pub fn find(name: &str) -> Option<&'static Recipe> {
+    recipes().iter().find(|recipe| recipe.name == name)
+}
+/// The recipes a crafter can make, in file order
+pub fn for_machine(machine: Tower) -> impl Iterator<Item = &'static Recipe> {
+    recipes().iter().filter(move |recipe| recipe.machine == machine)
+}
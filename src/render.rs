@@ -0,0 +1,238 @@
+use build_mode::BuildMode;
+use hashbrown::HashMap;
+use player::Player;
+use tiles::World;
+use tower::GuiCtx;
+
+use super::*;
+
+pub const BASE_TILE_SIZE: f32 = 48.;
+pub const STAR_PARTICLE_MAX_LIFETIME: f32 = 10.;
+pub const STAR_PARTICLE_MAX_AMOUNT: i32 = 200;
+
+/// Everything needed to draw a [`World`] and interact with it, needs a window
+pub struct WorldView {
+    enabled_gui: Option<IVec2>,
+    /// The machine waiting for the player to click on a cell, with the slot it asked for, see [`GuiCtx::request_pick`]
+    picking: Option<(IVec2, usize)>,
+    tilesize: f32,
+    star_particle: Texture2D,
+    star_particles: Vec<(Vec2,Vec2, Vec2, f32)>,
+    /// Celestial textures by asset path
+    celestial_textures: HashMap<String, Texture2D>,
+}
+impl WorldView {
+    pub async fn new(world: &World) -> Result<Self> {
+        let mut celestial_textures = HashMap::new();
+        for (_, celest) in world.celestials() {
+            let path = celest.texture_path().to_string();
+            if !celestial_textures.contains_key(&path) {
+                let texture = load_texture(&format!("assets/{}", path)).await?;
+                celestial_textures.insert(path, texture);
+            }
+        }
+        Ok(Self {
+            tilesize: BASE_TILE_SIZE,
+            enabled_gui: std::default::Default::default(),
+            picking: None,
+            star_particle: load_texture("assets/star_particle.png").await?,
+            star_particles: {
+                let mut star_particles = vec![];
+                for n in 0..STAR_PARTICLE_MAX_AMOUNT {
+                    let x = rand::gen_range(-20., 20.);
+                    let y = rand::gen_range(-20., 20.);
+                    let vx = rand::gen_range(-0.5, 0.5);
+                    let vy = rand::gen_range(-0.5, 0.5);
+                    let rot = macroquad::rand::gen_range(-360., 360.);
+                    let rot_vel = macroquad::rand::gen_range(-1.0, 1.);
+                    let lifetime = macroquad::rand::gen_range(0., 10.);
+                    star_particles.push((vec2(x,y),vec2(vx,vy),vec2(rot,rot_vel), lifetime))
+                }
+                star_particles
+            },
+            celestial_textures,
+        })
+    }
+    pub const fn tilesize(&self) -> f32 {self.tilesize}
+    pub fn tiles_in_screen(&self) -> Vec2 {
+        vec2(screen_width(), screen_height())/self.tilesize
+    }
+    pub fn screen_to_world(&self, scr: Vec2, player_cell: Vec2) -> IVec2 {
+        vec2i((scr/self.tilesize()+player_cell).floor())
+    }
+    pub fn world_to_screen_offset(&self, cell: IVec2, player_offset: Vec2) -> Vec2 {
+        vec2i_to_f(cell)*self.tilesize()-player_offset
+    }
+    pub fn world_to_screen(&self, cell: IVec2, player_cell: Vec2) -> Vec2 {
+        let player_offset = (player_cell.fract_gl()*self.tilesize()).abs();
+        self.world_to_screen_offset(cell, player_offset)-player_cell.floor()*self.tilesize()
+    }
+
+    pub async fn draw(&mut self, world: &World, player_cell: Vec2) -> Result<()> {
+        let player_offset = (player_cell.fract_gl()*self.tilesize()).abs();
+        let dest_size = Vec2::splat(self.tilesize());
+        let w_tiles = (screen_width() / self.tilesize()).ceil() as i32;
+        let h_tiles = (screen_height() / self.tilesize()).ceil() as i32;
+        self.draw_background_stars(player_cell);
+        // Only the chunks overlapping the screen are looked at, bigger machines can stick in from above and to the left
+        let top_left = vec2i(player_cell.floor());
+        for (_, chunk) in world.map().chunks_in(top_left-tower::MAX_TOWER_SIZE, top_left+ivec2(w_tiles, h_tiles)) {
+            for coords in chunk.coords() {
+                self.draw_tile(world, coords-top_left, player_cell, dest_size, player_offset)?;
+            }
+        }
+        // Entities are drawn over the machines
+        let screen = Rect::new(player_cell.x-1., player_cell.y-1., w_tiles as f32+2., h_tiles as f32+2.);
+        for entity in world.entities().in_rect(screen) {
+            entity.draw((entity.body.pos-player_cell)*self.tilesize(), self.tilesize());
+        }
+        for (coords, celest) in world.celestials() {
+            let Some(texture) = self.celestial_textures.get(celest.texture_path()) else {continue};
+            let coords = self.world_to_screen(*coords, player_cell);
+            draw_texture_ex(texture, coords.x, coords.y, WHITE, DrawTextureParams {
+                dest_size: Some(vec2i_to_f(celest.size)*self.tilesize()),
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+    fn draw_tile(&self, world: &World, tile: IVec2, player_cell: Vec2, dest_size: Vec2, player_offset: Vec2) -> Result<()> {
+        let c = tile + vec2i(player_cell.floor());
+        let (tx,ty) = tile.into();
+        let screen_pos = self.world_to_screen_offset(tile, player_offset);
+        // let perlin = noise::Perlin::new(self.seed as _);
+        // let density = noise::NoiseFn::get(&perlin, [tile.x as f64/10., tile.y as f64/10.]).abs()*200.;
+        // if density != 0. {dbg!(density);};
+        // let alpha = Color::from_rgba(255,255,255, density as u8);
+        // draw_rectangle(screen_pos.x,screen_pos.y, dest_size.x, dest_size.y, alpha);
+
+        let Some(machine) = world.try_get_tower(&c) else {return Ok(())};
+        let facing = world.facing(&c);
+        // A single sprite covers the whole machine
+        let size = machine.ty().size() as f32;
+        let text = machine.texture();
+        // let translated_x = cx as f32-world.tilesize()+offset.x;
+        // let translated_y = cy as f32-world.tilesize()+offset.y;
+        // let current_cell = (camera_pos+c/world.tilesize());
+        draw_texture_ex(&text, screen_pos.x,screen_pos.y, WHITE, DrawTextureParams {
+            dest_size: Some(dest_size*size),
+            rotation: facing.angle(),
+            ..Default::default()
+        });
+        machine.draw_overlay(screen_pos, self.tilesize()*size, facing);
+        Ok(())
+    }
+    fn draw_background_stars(&mut self, player_cell: Vec2) {
+        self.star_particles.retain(|(p, v,r, lifetime)| *lifetime <= STAR_PARTICLE_MAX_LIFETIME);
+
+        for n in 0..STAR_PARTICLE_MAX_AMOUNT-self.star_particles.len() as i32 {
+            let x = rand::gen_range(player_cell.x-1., player_cell.x+self.tiles_in_screen().x+1.);
+            let y = rand::gen_range(player_cell.y-1., player_cell.y+self.tiles_in_screen().y+1.);
+            let vx = rand::gen_range(-0.5, 0.5);
+            let vy = rand::gen_range(-0.5, 0.5);
+            let rot = macroquad::rand::gen_range(-360., 360.);
+            let rot_vel = macroquad::rand::gen_range(-1.0, 1.);
+            let lifetime = 0.;
+            self.star_particles.push((vec2(x,y),vec2(vx,vy),vec2(rot,rot_vel), lifetime))
+        }
+        for (pos, vel, rot, lifetime) in &mut self.star_particles {
+            *pos += *vel*get_frame_time();
+            if !Rect::new(player_cell.x-1.,player_cell.y-1., screen_width()/self.tilesize+2., screen_height()/self.tilesize+2.).contains(*pos) {
+                let (x,y) = player_cell.into();
+                let (w,h) = (screen_width()/self.tilesize, screen_height()/self.tilesize);
+                let p = *pos;
+
+                pos.x = if p.x < x-1. {
+                    rand::gen_range(player_cell.x+w-1., player_cell.x+w)
+                } else if p.x > x-1.+w+2. {
+                    rand::gen_range(player_cell.x-1., player_cell.x)
+                } else {pos.x};
+                pos.y = if p.y < y-1. {
+                    rand::gen_range(player_cell.y+h-1., player_cell.y+h)
+                } else if p.y > y-1.+h+2. {
+                    rand::gen_range(player_cell.y-1., player_cell.y)
+                } else {pos.y};
+
+            }
+            let pos = (*pos-player_cell)*self.tilesize;
+            let alpha = (255.0 * (1.0 - ((*lifetime - 5.0) / 5.0).powi(2))) as u8;
+            draw_texture_ex(&self.star_particle, pos.x, pos.y, Color::from_rgba(255, 255, 255, alpha), DrawTextureParams { dest_size: Some(vec2(0.25,0.25)*self.tilesize), rotation: rot.x,..Default::default() });
+            *lifetime += get_frame_time();
+            rot.x += rot.y*get_frame_time();
+        }
+    }
+    pub fn interact(&mut self, world: &mut World, player: &mut Player, player_cell: Vec2, build_mode: &BuildMode) -> Result<()> {
+        if is_key_released(KeyCode::Escape) {
+            self.enabled_gui = None;
+            self.picking = None;
+            return Ok(())
+        }
+        if let Some((home, slot)) = self.picking {
+            draw_text("Click on a machine, escape to cancel", 10., screen_height()-40., 32., WHITE);
+            if is_mouse_button_released(MouseButton::Left) {
+                let cell = self.screen_to_world(mouse_position().into(), player_cell);
+                if let Some(target) = world.anchor_of(&cell) {
+                    if let Some(machine) = world.try_get_tower_mut(&home) {
+                        machine.pick_cell(slot, target);
+                    }
+                    self.picking = None;
+                    self.enabled_gui = Some(home);
+                }
+            }
+            return Ok(())
+        }
+        let rect = if let Some(coords) = self.enabled_gui {
+            let grid = world.grid_stats(coords);
+            let Some(machine) = world.try_get_tower_mut(&coords) else {
+                self.enabled_gui = None;
+                return Ok(())
+            };
+            let mut ctx = GuiCtx::new(player).with_grid(grid);
+            let rect = machine.draw_gui(&mut ctx)?;
+            if let Some(slot) = ctx.pick_requested() {
+                self.picking = Some((coords, slot));
+            }
+            if ctx.close_requested() {
+                self.enabled_gui = None;
+            }
+            rect
+        } else {Rect::default()};
+        let mp = mouse_position().into();
+        // Rotates the machine under the mouse when not building
+        if build_mode.current == Tower::Empty && config::is_action_pressed(config::Action::Rotate) {
+            let cell = self.screen_to_world(mp, player_cell);
+            world.set_facing(&cell, world.facing(&cell).rotate_cw());
+        }
+        if is_mouse_button_released(MouseButton::Left) && build_mode.current == Tower::Empty && !rect.contains(mp) {
+            let cell = self.screen_to_world(mp, player_cell);
+            // Any cell of a bigger machine opens it
+            if let Some(cell) = world.anchor_of(&cell) {
+                if self.enabled_gui.is_some() && self.enabled_gui.unwrap() == cell {
+                    self.enabled_gui = None;
+                } else {
+                    self.enabled_gui.replace(cell);
+                }
+            }
+        }
+
+        Ok(())
+    }
+    pub fn control_tilesize(&mut self) -> Result<()> {
+        let (wx, mut wy) = mouse_wheel();
+        let zoom_factor = 1.1;
+
+        if wy > 0.0 { // Scroll up (zoom in)
+            self.tilesize *= zoom_factor;
+        } else if wy < 0.0 { // Scroll down (zoom out)
+            self.tilesize /= zoom_factor;
+        }
+
+        let min_tilesize = 3.0;
+        let max_tilesize = 256.0;
+        self.tilesize = self.tilesize.clamp(min_tilesize, max_tilesize);
+        Ok(())
+    }
+    pub fn remove_gui(&mut self) -> Option<IVec2> {
+        self.enabled_gui.take()
+    }
+}
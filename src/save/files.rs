@@ -0,0 +1,178 @@
+//! Where saves live on disk, written so that a crash never leaves a world half written
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::Context;
+
+use meta::WorldMeta;
+use tiles::World;
+
+use super::*;
+
+pub const SAVES_DIR: &str = "saves";
+pub const BACKUPS_DIR: &str = "backups";
+pub const TMP_EXTENSION: &str = "tmp";
+
+/// A folder of saves, with the backups of every world in a subfolder
+pub struct SaveDir {
+    root: PathBuf,
+}
+impl Default for SaveDir {
+    fn default() -> Self {
+        Self::new(SAVES_DIR)
+    }
+}
+impl SaveDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+    pub fn save_path(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+    pub fn backup_path(&self, name: &str, n: usize) -> PathBuf {
+        self.root.join(BACKUPS_DIR).join(format!("{}.{}", name, n))
+    }
+    /// Every save in the folder, without backups and unfinished writes
+    pub fn saves(&self) -> Result<Vec<PathBuf>> {
+        let mut saves = vec![];
+        for entry in std::fs::read_dir(&self.root)?.flatten() {
+            let path = entry.path();
+            if path.is_file() && path.extension().is_none_or(|ext| ext != TMP_EXTENSION) {
+                saves.push(path);
+            }
+        }
+        saves.sort();
+        Ok(saves)
+    }
+    /// The backups of a world, most recent first
+    pub fn backups(&self, name: &str) -> Vec<PathBuf> {
+        (1..).map(|n| self.backup_path(name, n)).take_while(|path| path.is_file()).collect()
+    }
+    /// Writes a save, keeping the previous one as the most recent of `backup_count` backups
+    pub fn write(&self, name: &str, raw: &str, backup_count: usize) -> Result<()> {
+        std::fs::create_dir_all(&self.root)?;
+        let path = self.save_path(name);
+        if backup_count > 0 && path.is_file() {
+            self.rotate_backups(name, backup_count)?;
+            std::fs::copy(&path, self.backup_path(name, 1)).context("Can't back up save")?;
+        }
+        write_atomic(&path, raw)
+    }
+    /// Shifts every backup by one, dropping the oldest ones so there is room for a new first backup
+    fn rotate_backups(&self, name: &str, backup_count: usize) -> Result<()> {
+        std::fs::create_dir_all(self.root.join(BACKUPS_DIR))?;
+        let existing = self.backups(name).len();
+        for n in (1..=existing).rev() {
+            if n >= backup_count {
+                std::fs::remove_file(self.backup_path(name, n))?;
+            } else {
+                std::fs::rename(self.backup_path(name, n), self.backup_path(name, n+1))?;
+            }
+        }
+        Ok(())
+    }
+    pub fn exists(&self, name: &str) -> bool {
+        self.save_path(name).exists()
+    }
+    /// A file name derived from the world name that no other save uses
+    pub fn unique_name(&self, world_name: &str) -> String {
+        let base = file_name_of(world_name);
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.exists(&name) {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        name
+    }
+    pub fn load(&self, name: &str) -> Result<World> {
+        let raw = std::fs::read_to_string(self.save_path(name)).with_context(|| format!("Can't read save {:?}", name))?;
+        let mut world = World::load(raw).with_context(|| format!("Can't load save {:?}", name))?;
+        world.set_save_name(name);
+        Ok(world)
+    }
+    pub fn read_save(&self, name: &str) -> Result<SaveFile> {
+        let raw = std::fs::read_to_string(self.save_path(name)).with_context(|| format!("Can't read save {:?}", name))?;
+        SaveFile::parse(&raw)
+    }
+    pub fn read_meta(&self, name: &str) -> Result<WorldMeta> {
+        Ok(self.read_save(name)?.meta)
+    }
+    /// Changes the name of a world, its file is renamed to match, returns the new file name
+    pub fn rename(&self, name: &str, world_name: &str) -> Result<String> {
+        let mut save = self.read_save(name)?;
+        save.meta.name = world_name.to_string();
+        let new_name = if file_name_of(world_name) == file_name_of(name) {name.to_string()} else {self.unique_name(world_name)};
+        write_atomic(&self.save_path(&new_name), &save.to_string()?)?;
+        if new_name != name {
+            for (n, backup) in self.backups(name).iter().enumerate() {
+                std::fs::rename(backup, self.backup_path(&new_name, n+1))?;
+            }
+            std::fs::remove_file(self.save_path(name))?;
+        }
+        Ok(new_name)
+    }
+    /// Copies a world under a new name, without its backups, returns the file name of the copy
+    pub fn duplicate(&self, name: &str) -> Result<String> {
+        let mut save = self.read_save(name)?;
+        save.meta.name = format!("{} (copy)", save.meta.name);
+        save.meta.created = meta::now();
+        let new_name = self.unique_name(&save.meta.name);
+        write_atomic(&self.save_path(&new_name), &save.to_string()?)?;
+        Ok(new_name)
+    }
+    /// Deletes a world and all of its backups
+    pub fn delete(&self, name: &str) -> Result<()> {
+        for backup in self.backups(name) {
+            std::fs::remove_file(backup)?;
+        }
+        std::fs::remove_file(self.save_path(name))?;
+        Ok(())
+    }
+    /// Replaces a world's save by one of its backups. The replaced save becomes the most recent backup like when saving,
+    /// so restoring the wrong one can be undone
+    pub fn restore_backup(&self, name: &str, backup: &Path, backup_count: usize) -> Result<()> {
+        let raw = std::fs::read_to_string(backup).with_context(|| format!("Can't read backup {:?}", backup))?;
+        self.write(name, &raw, backup_count.max(1))
+    }
+}
+
+/// Turns a world name into something safe to use as a file name
+pub fn file_name_of(world_name: &str) -> String {
+    let name: String = world_name.trim().chars().map(|c| if c.is_alphanumeric() || c == '-' {c.to_ascii_lowercase()} else {'_'}).collect();
+    if name.is_empty() {"world".to_string()} else {name}
+}
+
+/// Writes to a temporary file then renames it, so the file is either the old or the new one, never half written
+pub fn write_atomic(path: &Path, raw: &str) -> Result<()> {
+    use std::io::Write;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}", TMP_EXTENSION));
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut file = std::fs::File::create(&tmp).with_context(|| format!("Can't create {:?}", tmp))?;
+        file.write_all(raw.as_bytes())?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp, path).with_context(|| format!("Can't replace {:?}", path))?;
+    Ok(())
+}
+
+/// Counts time since the last save, and tells when it's time to save again
+pub struct Autosave {
+    elapsed: f32,
+}
+impl Autosave {
+    pub fn new() -> Self {
+        Self { elapsed: 0. }
+    }
+    /// Returns true when the world should be saved, an interval of 0 disables autosaving
+    pub fn update(&mut self, dt: f32, interval: f32) -> bool {
+        if interval <= 0. {return false}
+        self.elapsed += dt;
+        if self.elapsed >= interval {
+            self.elapsed = 0.;
+            return true
+        }
+        false
+    }
+}
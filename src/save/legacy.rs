@@ -0,0 +1,66 @@
+//! Importer for the hand-written save format used before saves were versioned:
+//! ```text
+//! Seed = 1022
+//! World = [
+//!     [-1, -1]: Electron {buffer: 40.193592}
+//! ]
+//! ```
+use color_eyre::eyre::{eyre, Context, ContextCompat};
+use toml::Spanned;
+
+use super::*;
+
+pub fn is_legacy(raw: &str) -> bool {
+    raw.starts_with("Seed = ")
+}
+
+pub fn import(raw: &str) -> Result<SaveFile> {
+    let mut lines = raw.lines().enumerate();
+    let (_, seed_line) = lines.next().context("Need seed information for world")?;
+    let seed = seed_line["Seed = ".len()..].trim().parse().context("Invalid seed (line 1)")?;
+    let (_, world_line) = lines.next().context("Need world map")?;
+    if world_line.trim() != "World = [" {
+        return Err(eyre!("Expected \"World = [\" (line 2), got {:?}", world_line))
+    }
+    let mut machines = Vec::new();
+    for (i, l) in lines {
+        let l = l.trim();
+        if l.is_empty() {continue}
+        if l == "]" {break}
+        let saved = parse_line(l).with_context(|| format!("Corrupt machine (line {}): {:?}", i+1, l))?;
+        machines.push(Spanned::new(0..0, saved));
+    }
+    Ok(SaveFile {
+        version: SAVE_VERSION,
+        seed,
+        meta: meta::WorldMeta {
+            name: format!("World {}", seed),
+            created: 0,
+            last_played: 0,
+            play_time: 0.,
+            thumbnail: None,
+        },
+        waves: Default::default(),
+        machines,
+        entities: Vec::new(),
+        source: raw.to_string(),
+    })
+}
+
+fn parse_line(l: &str) -> Result<SavedMachine> {
+    let x_end = l.find(",").context("Can't get x coordinate of machine")?;
+    let x = l[1..x_end].parse()?; // Skip [
+    let y_end = l.find("]: ").context("Can't get y coordinate of machine")?;
+    let y = l[x_end+2..y_end].parse()?; // Skip ", "
+    let tower = &l[y_end+3..];
+    let args_start = tower.find(" {").context("Can't get machine args")?;
+    let tower_ty = <Tower as std::str::FromStr>::from_str(&tower[..args_start])?;
+    let tower_args = tower[args_start+2..].strip_suffix("}").context("Machine args should end with }")?;
+    let machine = tower_ty.deserialize_legacy_machine(tower_args)?;
+    Ok(SavedMachine {
+        pos: [x, y],
+        tower: tower_ty,
+        facing: Direction::default(),
+        state: machine.serialize()?,
+    })
+}
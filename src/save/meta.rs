@@ -0,0 +1,99 @@
+//! Information about a world shown on the load screen
+use serde::{Deserialize, Serialize};
+use strum::EnumProperty;
+
+use tiles::World;
+
+use super::*;
+
+/// Width and height of the thumbnail, in pixels
+pub const THUMBNAIL_SIZE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldMeta {
+    pub name: String,
+    /// Unix timestamps, in seconds
+    pub created: u64,
+    pub last_played: u64,
+    /// Time spent in the world, in seconds
+    pub play_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Thumbnail>,
+}
+impl WorldMeta {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            created: now(),
+            last_played: now(),
+            play_time: 0.,
+            thumbnail: None,
+        }
+    }
+}
+
+/// A tiny top-down view of the machines of a world
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Thumbnail {
+    /// `THUMBNAIL_SIZE`² pixels as "rrggbb" hex, row by row
+    pub pixels: String,
+}
+impl Thumbnail {
+    /// Draws every machine around the base as a pixel, scaled down to fit the thumbnail
+    pub fn of(world: &World) -> Option<Self> {
+        let (min, max) = world.machines().fold(None, |bounds: Option<(IVec2, IVec2)>, (coords, _)| {
+            Some(bounds.map_or((coords, coords), |(min, max)| (min.min(coords), max.max(coords))))
+        })?;
+        let cell = ((max-min).max_element() as usize/THUMBNAIL_SIZE+1) as i32;
+        let mut pixels = vec![None; THUMBNAIL_SIZE*THUMBNAIL_SIZE];
+        for (coords, machine) in world.machines() {
+            let p = (coords-min)/cell;
+            pixels[p.y as usize*THUMBNAIL_SIZE+p.x as usize] = Some(machine.ty().map_color());
+        }
+        let mut hex = String::with_capacity(pixels.len()*6);
+        for pixel in pixels {
+            let [r, g, b, _]: [u8; 4] = pixel.unwrap_or(BLACK).into();
+            hex.push_str(&format!("{:02x}{:02x}{:02x}", r, g, b));
+        }
+        Some(Self { pixels: hex })
+    }
+    /// RGBA bytes, ready to be turned into a texture
+    pub fn rgba(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(THUMBNAIL_SIZE*THUMBNAIL_SIZE*4);
+        for i in (0..self.pixels.len()).step_by(6) {
+            for c in 0..3 {
+                bytes.push(self.pixels.get(i+c*2..i+c*2+2).and_then(|h| u8::from_str_radix(h, 16).ok()).unwrap_or(0));
+            }
+            bytes.push(255);
+        }
+        bytes.resize(THUMBNAIL_SIZE*THUMBNAIL_SIZE*4, 0);
+        bytes
+    }
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Formats a unix timestamp as "YYYY-MM-DD HH:MM" (UTC)
+pub fn format_date(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+    // From Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);
+    let mp = (5*doy + 2)/153;
+    let day = doy - (153*mp+2)/5 + 1;
+    let month = if mp < 10 {mp+3} else {mp-9};
+    let year = yoe + era*400 + if month <= 2 {1} else {0};
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, secs/3600, secs%3600/60)
+}
+
+/// Formats a duration in seconds as "1h 02m"
+pub fn format_duration(secs: f64) -> String {
+    let mins = (secs / 60.) as u64;
+    format!("{}h {:02}m", mins/60, mins%60)
+}
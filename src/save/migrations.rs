@@ -0,0 +1,120 @@
+//! Upgrades saves written by older versions of the game, one version at a time
+use color_eyre::eyre::{eyre, ContextCompat};
+
+use super::*;
+
+/// Upgrades a save from one version to the next one, the version key is updated by [`migrate`]
+pub type Migration = fn(&mut toml::Table) -> Result<()>;
+
+/// The migration upgrading saves from the version to the next one, in order
+pub const MIGRATIONS: &[(u32, Migration)] = &[
+    (1, v1_persist_collect_speed),
+    (2, v2_add_world_meta),
+    (3, v3_move_facing_out_of_state),
+    (4, v4_drones_become_entities),
+    (5, v5_item_deposit_is_built_in),
+];
+
+/// Upgrades the save to [`SAVE_VERSION`], returns the version it was saved with
+pub fn migrate(save: &mut toml::Table) -> Result<u32> {
+    let original = save_version(save)?;
+    if original > SAVE_VERSION {
+        return Err(eyre!("Unsupported save version {} (expected {} or older)", original, SAVE_VERSION))
+    }
+    let mut version = original;
+    while version < SAVE_VERSION {
+        let (_, migration) = MIGRATIONS.iter().find(|(v, _)| *v == version).with_context(|| format!("No migration from save version {}", version))?;
+        migration(save).with_context(|| format!("Can't migrate save from version {} to {}", version, version+1))?;
+        version += 1;
+        save.insert("version".into(), toml::Value::Integer(version as i64));
+    }
+    Ok(original)
+}
+
+pub fn save_version(save: &toml::Table) -> Result<u32> {
+    let version = save.get("version").context("Save has no version")?.as_integer().context("Save version should be an integer")?;
+    Ok(version.try_into()?)
+}
+
+/// Calls `f` with the state of every machine of this tower type
+fn for_each_state(save: &mut toml::Table, tower: &str, mut f: impl FnMut(&mut toml::Table) -> Result<()>) -> Result<()> {
+    let Some(machines) = save.get_mut("machines") else {return Ok(())};
+    let machines = machines.as_array_mut().context("machines should be an array")?;
+    for machine in machines {
+        let machine = machine.as_table_mut().context("machine should be a table")?;
+        if machine.get("tower").and_then(toml::Value::as_str) != Some(tower) {continue}
+        let state = machine.entry("state").or_insert_with(|| toml::Table::new().into());
+        f(state.as_table_mut().context("machine state should be a table")?)?;
+    }
+    Ok(())
+}
+
+/// Electrons now save their collect speed, older ones all collected at the default speed
+fn v1_persist_collect_speed(save: &mut toml::Table) -> Result<()> {
+    for_each_state(save, "Electron", |state| {
+        state.entry("collect_speed").or_insert(toml::Value::Float(1.));
+        Ok(())
+    })
+}
+
+/// Worlds now have a name and metadata, older ones are named after their seed
+fn v2_add_world_meta(save: &mut toml::Table) -> Result<()> {
+    let seed = save.get("seed").and_then(toml::Value::as_integer).context("Save has no seed")?;
+    let mut meta = toml::Table::new();
+    meta.insert("name".into(), format!("World {}", seed as u64).into());
+    meta.insert("created".into(), 0.into());
+    meta.insert("last_played".into(), 0.into());
+    meta.insert("play_time".into(), 0.0.into());
+    save.entry("meta").or_insert(meta.into());
+    Ok(())
+}
+
+/// Every machine now has a facing, it used to be part of the state of conveyors and pipes
+fn v3_move_facing_out_of_state(save: &mut toml::Table) -> Result<()> {
+    let Some(machines) = save.get_mut("machines") else {return Ok(())};
+    let machines = machines.as_array_mut().context("machines should be an array")?;
+    for machine in machines {
+        let machine = machine.as_table_mut().context("machine should be a table")?;
+        let facing = machine.get_mut("state").and_then(toml::Value::as_table_mut).and_then(|state| state.remove("facing"));
+        machine.insert("facing".into(), facing.unwrap_or_else(|| "Right".into()));
+    }
+    Ok(())
+}
+
+/// Drones are now entities with an id, their position is out of their state
+fn v4_drones_become_entities(save: &mut toml::Table) -> Result<()> {
+    let Some(drones) = save.remove("drones") else {return Ok(())};
+    let drones = drones.try_into::<Vec<toml::Table>>().context("drones should be an array of tables")?;
+    let mut entities = Vec::with_capacity(drones.len());
+    for (id, mut state) in drones.into_iter().enumerate() {
+        let pos = state.remove("pos").context("drone should have a pos")?;
+        let mut entity = toml::Table::new();
+        entity.insert("id".into(), (id as i64).into());
+        entity.insert("type".into(), "Drone".into());
+        entity.insert("pos".into(), pos);
+        entity.insert("state".into(), state.into());
+        entities.push(toml::Value::Table(entity));
+    }
+    save.insert("entities".into(), entities.into());
+    Ok(())
+}
+
+/// The item deposit used to be declared in `assets/towers` as "Item deposit", it is now [`Tower::ItemDeposit`]
+/// with a capacity instead of the stack size of its item
+fn v5_item_deposit_is_built_in(save: &mut toml::Table) -> Result<()> {
+    let Some(machines) = save.get_mut("machines") else {return Ok(())};
+    let machines = machines.as_array_mut().context("machines should be an array")?;
+    for machine in machines {
+        let machine = machine.as_table_mut().context("machine should be a table")?;
+        if machine.get("tower").and_then(toml::Value::as_str) != Some("Item deposit") {continue}
+        machine.insert("tower".into(), Tower::ItemDeposit.name().into());
+    }
+    for_each_state(save, &Tower::ItemDeposit.name(), |state| {
+        state.entry("level").or_insert(0.into());
+        if let Some(inventory) = state.get_mut("inventory") {
+            let inventory = inventory.as_table_mut().context("inventory should be a table")?;
+            inventory.entry("stack_limit").or_insert((tower::item_deposit::ITEM_DEPOSIT_CAPACITY as i64).into());
+        }
+        Ok(())
+    })
+}
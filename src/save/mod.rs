@@ -0,0 +1,144 @@
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use toml::Spanned;
+
+use chunk::Placed;
+use direction::Direction;
+use entity::{Body, Entity, EntityId, EntityType};
+use tiles::{DynMachine, World};
+
+use super::*;
+
+pub mod legacy;
+pub mod migrations;
+pub mod files;
+pub mod meta;
+
+/// Bumped every time the layout of a save or the state of a machine changes,
+/// along with a new migration in [`migrations::MIGRATIONS`] and a fixture save in `tests/fixtures/saves`
+pub const SAVE_VERSION: u32 = 6;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    #[serde(with = "seed_as_i64")]
+    pub seed: u64,
+    pub meta: meta::WorldMeta,
+    /// Worlds from before shifters have none
+    #[serde(default)]
+    pub waves: enemy::Waves,
+    #[serde(default)]
+    pub machines: Vec<Spanned<SavedMachine>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<SavedEntity>,
+    /// The text the spans of the machines point into, used for errors
+    #[serde(skip)]
+    source: String,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedMachine {
+    pub pos: [i32; 2],
+    pub tower: Tower,
+    #[serde(default)]
+    pub facing: Direction,
+    /// Whatever [`tower::Machine::serialize`] returned
+    #[serde(default)]
+    pub state: toml::Table,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedEntity {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub ty: EntityType,
+    pub pos: [f32; 2],
+    #[serde(default)]
+    pub vel: [f32; 2],
+    /// Whatever [`entity::Behaviour::serialize`] returned
+    #[serde(default)]
+    pub state: toml::Table,
+}
+
+impl SaveFile {
+    pub fn from_world(world: &mut World) -> Result<Self> {
+        let mut machines: Vec<_> = world.saved_machines()?.into_iter().map(|m| Spanned::new(0..0, m)).collect();
+        // Keeps saves stable between runs, the map iteration order is random
+        machines.sort_by_key(|m| {let [x, y] = m.get_ref().pos; (y, x)});
+        Ok(Self {
+            version: SAVE_VERSION,
+            seed: world.seed(),
+            meta: world.meta().clone(),
+            waves: world.waves().clone(),
+            machines,
+            entities: world.entities().iter().map(|entity| Ok(SavedEntity {
+                id: entity.id().0,
+                ty: entity.ty(),
+                pos: entity.body.pos.into(),
+                vel: entity.body.vel.into(),
+                state: entity.behaviour().serialize().with_context(|| format!("Can't save {:?} {:?}", entity.ty(), entity.id()))?,
+            })).collect::<Result<_>>()?,
+            source: String::new(),
+        })
+    }
+    pub fn to_string(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+    /// Parses a save, either in the current format, an older one that gets migrated or the legacy one
+    pub fn parse(raw: &str) -> Result<Self> {
+        if legacy::is_legacy(raw) {
+            return legacy::import(raw)
+        }
+        let mut table: toml::Table = toml::from_str(raw).map_err(|e| eyre!("Corrupt save: {}", e))?;
+        let source = if migrations::migrate(&mut table)? == SAVE_VERSION {
+            raw.to_string()
+        } else {
+            // Line numbers in errors will point into the migrated save
+            toml::to_string(&table)?
+        };
+        let mut save: Self = toml::from_str(&source).map_err(|e| eyre!("Corrupt save: {}", e))?;
+        save.source = source;
+        Ok(save)
+    }
+    /// Builds every entity of the save, failing on the first invalid one
+    pub fn entities(&self) -> Result<Vec<Entity>> {
+        self.entities.iter().map(|saved| {
+            let behaviour = saved.ty.deserialize_behaviour(saved.state.clone())
+                .with_context(|| format!("Corrupt {:?} {}", saved.ty, saved.id))?;
+            Ok(Entity::new(EntityId(saved.id), Body { pos: saved.pos.into(), vel: saved.vel.into() }, behaviour))
+        }).collect()
+    }
+    /// Builds every machine of the save, failing on the first invalid one
+    pub fn into_machines(self) -> Result<Vec<(IVec2, Placed)>> {
+        let mut machines = Vec::with_capacity(self.machines.len());
+        for saved in self.machines {
+            let span = saved.span();
+            let saved = saved.into_inner();
+            let coords = IVec2::from(saved.pos);
+            let machine = saved.tower.deserialize_machine(saved.state).with_context(|| {
+                let line = self.source.get(..span.start).map_or(0, |before| before.matches('\n').count()+1);
+                format!("Corrupt {:?} at {} (line {})", saved.tower, coords, line)
+            })?;
+            machines.push((coords, Placed { machine, facing: saved.facing }));
+        }
+        Ok(machines)
+    }
+}
+
+/// TOML integers are i64, seeds are stored with the same bits so any u64 fits
+mod seed_as_i64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(*seed as i64)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        Ok(i64::deserialize(deserializer)? as u64)
+    }
+}
+
+/// Helper for machines that can store their whole state with serde
+pub fn to_state<T: Serialize>(state: &T) -> Result<toml::Table> {
+    Ok(toml::Table::try_from(state)?)
+}
+pub fn from_state<T: serde::de::DeserializeOwned>(state: toml::Table) -> Result<T> {
+    Ok(state.try_into()?)
+}
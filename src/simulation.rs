@@ -0,0 +1,105 @@
+use hashbrown::HashMap;
+use item::ItemStack;
+
+use super::*;
+
+/// How many machines are updated each tick by default, in [`SimulationMode::Factory`]
+pub const DEFAULT_SIMULATION_BUDGET: usize = 5000;
+/// The budgets the options menu cycles through
+pub const SIMULATION_BUDGETS: [usize; 4] = [1000, 5000, 20000, 100000];
+/// How long the game can catch up on after being closed by default, in seconds
+pub const DEFAULT_MAX_OFFLINE_TIME: f64 = 8.*3600.;
+/// The limits the options menu cycles through, 0 disables offline progress
+pub const MAX_OFFLINE_TIMES: [f64; 4] = [3600., 8.*3600., 24.*3600., 0.];
+/// Offline time is simulated in steps this long, so machines still get to pass items to each other
+pub const OFFLINE_STEP: f32 = 60.;
+/// Entities are moved in much shorter steps than machines while offline, they only do one thing per step
+pub const ENTITY_OFFLINE_STEP: f32 = 0.1;
+
+#[derive(strum_macros::EnumString, strum_macros::Display, strum_macros::EnumIter, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum SimulationMode {
+    /// Only the machines near the player and in active chunks run, the rest of the world is frozen
+    Nearby,
+    /// Every machine runs, the ones that don't fit in the budget are fast-forwarded from time to time
+    #[default]
+    Factory,
+}
+
+/// Decides which chunks are simulated each tick, and how precisely
+pub struct Simulation {
+    pub mode: SimulationMode,
+    /// How many machines can be updated in a single tick, the chunks near the player always are
+    pub budget: usize,
+    /// Time since each distant chunk was last simulated, in seconds
+    behind: HashMap<IVec2, f32>,
+}
+impl Simulation {
+    pub fn new() -> Self {
+        Self {
+            mode: SimulationMode::default(),
+            budget: DEFAULT_SIMULATION_BUDGET,
+            behind: HashMap::new(),
+        }
+    }
+    /// How far behind a chunk is, 0 if it is simulated every tick
+    pub fn behind(&self, chunk: IVec2) -> f32 {
+        self.behind.get(&chunk).copied().unwrap_or(0.)
+    }
+    /// The time a chunk has to catch up on, it is considered up to date afterwards
+    pub fn catch_up(&mut self, chunk: IVec2) -> f32 {
+        self.behind.remove(&chunk).unwrap_or(0.)
+    }
+    /// Splits the chunks into the ones updated every tick and the ones fast-forwarded this tick.
+    /// `nearby` are always updated every tick, `others` are all the remaining chunks with their machine count.
+    pub fn plan(&mut self, player_chunk: IVec2, nearby: Vec<IVec2>, nearby_machines: usize, mut others: Vec<(IVec2, usize)>, dt: f32) -> Plan {
+        let mut plan = Plan { exact: nearby, fast_forward: Vec::new() };
+        if self.mode == SimulationMode::Nearby {
+            return plan
+        }
+        let mut budget = self.budget.saturating_sub(nearby_machines);
+        // Closest first, the ones that don't fit anymore fall back to fast-forwarding
+        others.sort_by_key(|(chunk, _)| (*chunk-player_chunk).abs().max_element());
+        let mut distant = Vec::new();
+        for (chunk, len) in others {
+            if distant.is_empty() && len <= budget {
+                budget -= len;
+                plan.exact.push(chunk);
+            } else {
+                *self.behind.entry(chunk).or_default() += dt;
+                distant.push((chunk, len));
+            }
+        }
+        // The chunks that waited the longest go first, at least one per tick so everything moves eventually
+        distant.sort_by(|(a, _), (b, _)| self.behind(*b).total_cmp(&self.behind(*a)));
+        for (chunk, len) in distant {
+            if !plan.fast_forward.is_empty() && len > budget {break}
+            budget = budget.saturating_sub(len);
+            plan.fast_forward.push(chunk);
+        }
+        plan
+    }
+}
+
+/// What [`Simulation::plan`] decided for a tick
+pub struct Plan {
+    /// Updated for a single tick
+    pub exact: Vec<IVec2>,
+    /// Fast-forwarded by the time they missed
+    pub fast_forward: Vec<IVec2>,
+}
+
+/// What happened while the game was closed, see [`tiles::World::offline_progress`]
+#[derive(Debug)]
+pub struct OfflineReport {
+    /// How long the game was closed, in seconds
+    pub away: f64,
+    /// How much of it was simulated, less than `away` when over the limit
+    pub simulated: f64,
+    /// The items that appeared in machines
+    pub produced: Vec<ItemStack>,
+}
+impl OfflineReport {
+    pub fn is_empty(&self) -> bool {
+        self.simulated <= 0.
+    }
+}
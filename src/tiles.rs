@@ -1,22 +1,29 @@
 
 use std::{cell::{Cell, OnceCell, RefCell}, rc::Rc, sync::{Arc, Mutex, MutexGuard}};
 
-use build_mode::BuildMode;
 use celestial::{parse_celestials, Celestial};
-use color_eyre::eyre::ContextCompat;
-use tower::{EmptyMachine, Machine};
+use save::meta::{Thumbnail, WorldMeta};
+use simulation::{OfflineReport, Simulation};
+use energy::{EnergyNetwork, GridStats};
+use direction::Direction;
+use entity::Entities;
+use enemy::{Difficulty, Shifter, Waves};
+use pathfinding::Pathfinder;
+use item::{Inventory, Item, ItemStack};
+use tower::{EmptyMachine, Machine, UpdateCtx, WorldCommand};
+
+use color_eyre::eyre::Context;
 
 use super::*;
 
-pub const BASE_TILE_SIZE: f32 = 48.;
 pub const BASE_UPDATE_RADIUS: usize = 50;
+/// How far from spawn celestials can be generated, in tiles
+pub const CELESTIAL_MIN_DISTANCE: f32 = 15.;
+pub const CELESTIAL_MAX_DISTANCE: f32 = 40.;
 
-pub type DynMachine = Rc<dyn Machine>;
+pub type DynMachine = Box<dyn Machine>;
 pub fn new_machine(machine: impl Machine + 'static) -> DynMachine {
-    Rc::new(machine)
-}
-thread_local! {
-    pub static EMPTY_MACHINE: OnceCell<DynMachine> = OnceCell::new();
+    Box::new(machine)
 }
 pub static mut WORLD: Option<World> = None;
 #[macro_export]
@@ -28,285 +35,365 @@ macro_rules! get_world {
 pub fn set_world(world: World) {
     unsafe { tiles::WORLD.replace(world) };
 }
-pub type Map = hashbrown::HashMap<IVec2, DynMachine>;
-pub const STAR_PARTICLE_MAX_LIFETIME: f32 = 10.;
-pub const STAR_PARTICLE_MAX_AMOUNT: i32 = 200;
+pub type Map = chunk::ChunkMap;
+/// The simulation state of a world, it doesn't need a window, see [`render::WorldView`] for drawing it
 pub struct World {
     map: Map,
     seed: u64,
-    enabled_gui: Option<IVec2>,
-    tilesize: f32,
+    meta: WorldMeta,
+    /// The file name of the world in the saves folder, chosen on the first save
+    save_name: Option<String>,
     update_radius: usize,
+    simulation: Simulation,
+    energy: EnergyNetwork,
     celestials: Vec<(IVec2, Celestial)>,
-    star_particle: Texture2D,
-    star_particles: Vec<(Vec2,Vec2, Vec2, f32)>,
+    /// Used for everything generated from the seed
+    rng: WorldRng,
+    /// The last saved state of every chunk, only dirty chunks are serialized again
+    saved_chunks: hashbrown::HashMap<IVec2, Vec<save::SavedMachine>>,
+    /// What moves freely over the map, e.g. drones
+    entities: Entities,
+    waves: Waves,
+    /// Paths around the machines, forgotten as machines come and go
+    pathfinder: Pathfinder,
 }
 impl World {
-    pub const fn tilesize(&self) -> f32 {self.tilesize}
-    pub fn tiles_in_screen(&self) -> Vec2 {
-        vec2(screen_width(), screen_height())/self.tilesize
-    }
-    pub async fn new(seed: u64) -> Self {
-        let celestials = parse_celestials().await;
+    pub fn new(seed: u64) -> Self {
+        let mut rng = WorldRng::new(seed);
+        let celestials = parse_celestials();
         Self { 
             seed,
-            tilesize: BASE_TILE_SIZE, 
+            meta: WorldMeta::new(format!("World {}", seed)),
+            save_name: None,
             update_radius: BASE_UPDATE_RADIUS,
+            simulation: Simulation::new(),
+            energy: EnergyNetwork::new(),
             celestials: {
                 let mut c = vec![];
                 for celest in celestials {
-                    c.push((ivec2(20, 0), celest));
+                    let angle = rng.gen_range(0., std::f32::consts::TAU);
+                    let distance = rng.gen_range(CELESTIAL_MIN_DISTANCE, CELESTIAL_MAX_DISTANCE);
+                    c.push((vec2i(Vec2::from_angle(angle)*distance), celest));
                 }
                 c
             },
             map: std::default::Default::default(),
-            enabled_gui: std::default::Default::default(),
-            star_particle: load_texture("assets/star_particle.png").await.unwrap(),
-            star_particles: {
-                let mut star_particles = vec![];
-                for n in 0..STAR_PARTICLE_MAX_AMOUNT {
-                    let x = rand::gen_range(-20., 20.);
-                    let y = rand::gen_range(-20., 20.);
-                    let vx = rand::gen_range(-0.5, 0.5);
-                    let vy = rand::gen_range(-0.5, 0.5);
-                    let rot = macroquad::rand::gen_range(-360., 360.);
-                    let rot_vel = macroquad::rand::gen_range(-1.0, 1.);
-                    let lifetime = macroquad::rand::gen_range(0., 10.);
-                    star_particles.push((vec2(x,y),vec2(vx,vy),vec2(rot,rot_vel), lifetime))
-                }
-                star_particles
-            },
+            rng,
+            saved_chunks: std::default::Default::default(),
+            entities: Entities::new(),
+            waves: Waves::default(),
+            pathfinder: Pathfinder::new(),
         }
     }
+    pub const fn seed(&self) -> u64 {self.seed}
+    pub fn meta(&self) -> &WorldMeta {&self.meta}
+    pub fn meta_mut(&mut self) -> &mut WorldMeta {&mut self.meta}
+    pub fn celestials(&self) -> &[(IVec2, Celestial)] {&self.celestials}
+    pub fn machine_count(&self) -> usize {self.map.len()}
+    pub fn machines(&self) -> impl Iterator<Item = (IVec2, &dyn Machine)> {
+        self.map.iter().map(|(coords, machine)| (coords, &**machine))
+    }
+    pub fn map(&self) -> &Map {&self.map}
+    pub fn entities(&self) -> &Entities {&self.entities}
+    pub fn entities_mut(&mut self) -> &mut Entities {&mut self.entities}
+    pub fn waves(&self) -> &Waves {&self.waves}
+    pub fn pathfinder(&self) -> &Pathfinder {&self.pathfinder}
+    /// The cells to walk through to reach the machine covering `to`, see [`Pathfinder::find`]
+    pub fn find_path(&mut self, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+        self.pathfinder.find(&self.map, from, to)
+    }
+    /// Starts the countdown to the first wave again
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.waves = Waves::new(difficulty);
+    }
+    pub fn simulation(&self) -> &Simulation {&self.simulation}
+    pub fn simulation_mut(&mut self) -> &mut Simulation {&mut self.simulation}
+    pub fn energy(&self) -> &EnergyNetwork {&self.energy}
+    /// The power grid the machine at these coordinates is part of
+    pub fn grid_stats(&self, coords: IVec2) -> Option<GridStats> {
+        self.energy.stats(self.map.anchor_of(&coords)?)
+    }
+    /// Active chunks keep updating when the player is far away
+    pub fn set_chunk_active(&mut self, chunk: IVec2, active: bool) {
+        self.map.set_active(chunk, active);
+    }
+    /// Bigger machines are anchored at `coords` and cover the cells below and to the right of it.
+    /// Whatever was on the covered cells is removed, the machine that was at `coords` is returned
     pub fn set_tower(&mut self, coords: IVec2, machine: impl Into<DynMachine>) -> Option<DynMachine> {
         let machine = machine.into();
+        self.energy.invalidate();
+        let prev = self.remove_structure(&coords);
         if machine.ty() == Tower::Empty {
-            self.map.remove(&coords)
-        } else {
-            self.map.insert(coords, machine)
+            return prev
+        }
+        for cell in machine.ty().footprint(coords).skip(1) {
+            self.remove_structure(&cell);
+        }
+        for cell in machine.ty().footprint(coords) {
+            self.pathfinder.invalidate(cell);
         }
+        self.occupy(coords, machine.ty());
+        self.map.insert(coords, machine);
+        prev
     }
-    #[track_caller]
-    pub fn get_tower(&self, coords: &IVec2) -> DynMachine {
-        if let Some(machine) = self.map.get(coords) {
-            machine.clone()
-        } else {
-            EMPTY_MACHINE.with(|cell| {
-                cell.get().unwrap().clone()
-            })
+    /// Removes the whole machine covering this cell
+    fn remove_structure(&mut self, coords: &IVec2) -> Option<DynMachine> {
+        let anchor = self.map.anchor_of(coords)?;
+        let machine = self.map.remove(&anchor)?;
+        for cell in machine.ty().footprint(anchor).skip(1) {
+            self.map.remove_part(&cell);
+        }
+        for cell in machine.ty().footprint(anchor) {
+            self.pathfinder.invalidate(cell);
+        }
+        Some(machine)
+    }
+    /// Points the cells covered by a machine to its anchor
+    fn occupy(&mut self, anchor: IVec2, tower: Tower) {
+        for cell in tower.footprint(anchor).skip(1) {
+            self.map.insert_part(cell, anchor);
         }
     }
-    #[track_caller]
-    pub fn try_get_tower(&self, coords: &IVec2) -> Option<DynMachine> {
-        self.map.get(coords).cloned()
+    /// Whether a tower anchored at `coords` would only cover empty cells
+    pub fn fits(&self, coords: IVec2, tower: Tower) -> bool {
+        tower.footprint(coords).all(|cell| self.map.anchor_of(&cell).is_none())
+    }
+    /// The anchor of the machine covering this cell
+    pub fn anchor_of(&self, coords: &IVec2) -> Option<IVec2> {
+        self.map.anchor_of(coords)
+    }
+    /// Where the machine at these coordinates faces, the default direction if there is none
+    pub fn facing(&self, coords: &IVec2) -> Direction {
+        self.map.facing(coords).unwrap_or_default()
+    }
+    pub fn set_facing(&mut self, coords: &IVec2, facing: Direction) {
+        self.map.set_facing(coords, facing);
+    }
+    pub fn get_tower(&self, coords: &IVec2) -> &dyn Machine {
+        self.try_get_tower(coords).unwrap_or(&EmptyMachine {})
+    }
+    pub fn try_get_tower(&self, coords: &IVec2) -> Option<&dyn Machine> {
+        self.map.get(coords).map(|machine| &**machine)
+    }
+    pub fn try_get_tower_mut(&mut self, coords: &IVec2) -> Option<&mut DynMachine> {
+        self.map.get_mut(coords)
     }
     pub async fn get_tower_texture(&self, coords: &IVec2) -> Texture2D {
         self.get_tower(coords).texture()
     }
-    pub fn screen_to_world(&self, scr: Vec2, player_cell: Vec2) -> IVec2 {
-        vec2i((scr/self.tilesize()+player_cell).floor())
-    }
-    pub fn world_to_screen_offset(&self, cell: IVec2, player_offset: Vec2) -> Vec2 {
-        vec2i_to_f(cell)*self.tilesize()-player_offset
-    }
-    pub fn world_to_screen(&self, cell: IVec2, player_cell: Vec2) -> Vec2 {
-        let player_offset = (player_cell.fract_gl()*self.tilesize()).abs();
-        self.world_to_screen_offset(cell, player_offset)-player_cell.floor()*self.tilesize()
-    }
-    
-    pub async fn draw(&mut self, player_cell: Vec2) -> Result<()> {
-        let player_offset = (player_cell.fract_gl()*self.tilesize()).abs();
-        let dest_size = Vec2::splat(self.tilesize());
-        let w_tiles = (screen_width() / self.tilesize()).ceil() as i32;
-        let h_tiles = (screen_height() / self.tilesize()).ceil() as i32;
-        self.draw_background_stars(player_cell);
-        if ((w_tiles*h_tiles) as usize) < self.map.len() {
-            for tx in -1..=w_tiles {
-                for ty in -1..=h_tiles {
-                    self.draw_tile(ivec2(tx,ty), player_cell, dest_size, player_offset)?;
+
+    pub fn update(&mut self, player_cell: Vec2, dt: f32) -> Result<()> {
+        let plan = {
+            let half = self.update_radius as i32/2;
+            let player = vec2i(player_cell);
+            let mut nearby: Vec<IVec2> = self.map.chunks_in(player-half, player+half).map(|(coords, _)| coords).collect();
+            for chunk in self.map.active_chunks() {
+                if !nearby.contains(&chunk) {
+                    nearby.push(chunk);
                 }
             }
-        } else {
-            for tile in self.map.keys().cloned().collect::<Vec<IVec2>>() {
-                self.draw_tile(tile-vec2i(player_cell.floor()), player_cell, dest_size, player_offset)?;
+            let nearby_machines = nearby.iter().filter_map(|c| self.map.chunk(*c)).map(chunk::Chunk::len).sum();
+            let others = self.map.chunks()
+                .filter(|(coords, chunk)| !chunk.is_empty() && !nearby.contains(coords))
+                .map(|(coords, chunk)| (coords, chunk.len()))
+                .collect();
+            self.simulation.plan(chunk::chunk_of(player), nearby, nearby_machines, others, dt)
+        };
+        // Every grid is solved each tick, distant consumers keep what they got until they are fast-forwarded
+        self.energy.solve(&mut self.map, dt);
+        let mut commands = Vec::new();
+        for chunk in plan.exact {
+            let behind = self.simulation.catch_up(chunk);
+            if behind > 0. {
+                self.update_chunk(chunk, behind, true, &mut commands)?;
             }
+            self.update_chunk(chunk, dt, false, &mut commands)?;
+        }
+        for chunk in plan.fast_forward {
+            let behind = self.simulation.catch_up(chunk);
+            self.update_chunk(chunk, behind, true, &mut commands)?;
         }
-        for (coords, celest) in &self.celestials {
-            let coords = self.world_to_screen(*coords, player_cell);
-            draw_texture_ex(celest.texture(), coords.x, coords.y, WHITE, DrawTextureParams {
-                dest_size: Some(vec2i_to_f(celest.size)*self.tilesize()),
-                ..Default::default()
-            });
+        self.entities.update(&mut self.map, &mut self.pathfinder, &mut commands, dt)?;
+        self.apply_commands(commands);
+        // Waves only come while playing, not during offline progress
+        if let Some(size) = self.waves.update(self.energy.production(), dt) {
+            self.spawn_wave(size);
         }
         Ok(())
     }
-    fn draw_tile(&mut self, tile: IVec2, player_cell: Vec2, dest_size: Vec2, player_offset: Vec2) -> Result<()> {
-        let c = tile + vec2i(player_cell.floor());
-        let (tx,ty) = tile.into();
-        let screen_pos = self.world_to_screen_offset(tile, player_offset);
-        // let perlin = noise::Perlin::new(self.seed as _);
-        // let density = noise::NoiseFn::get(&perlin, [tile.x as f64/10., tile.y as f64/10.]).abs()*200.;
-        // if density != 0. {dbg!(density);};
-        // let alpha = Color::from_rgba(255,255,255, density as u8);
-        // draw_rectangle(screen_pos.x,screen_pos.y, dest_size.x, dest_size.y, alpha);
-        
-        let text = if let Some(machine) = self.map.get(&c) {
-            machine.texture()
-        } else {return Ok(())};
-        // let translated_x = cx as f32-world.tilesize()+offset.x;
-        // let translated_y = cy as f32-world.tilesize()+offset.y;
-        // let current_cell = (camera_pos+c/world.tilesize());
-        draw_texture_ex(&text, screen_pos.x,screen_pos.y, WHITE, DrawTextureParams {
-            dest_size: Some(dest_size),
-            ..Default::default()
-        });
+    /// Sends `size` shifters from a random side of the explored area
+    pub fn spawn_wave(&mut self, size: u32) {
+        let radius = enemy::spawn_radius(&self.map);
+        let center = Vec2::from_angle(self.rng.gen_range(0., std::f32::consts::TAU))*radius;
+        for _ in 0..size {
+            let offset = vec2(self.rng.gen_range(-2., 2.), self.rng.gen_range(-2., 2.));
+            self.entities.spawn(center+offset, Box::new(Shifter::new()));
+        }
+    }
+    /// Updates every machine of a chunk, `fast_forward` uses [`Machine::fast_forward`] for long durations
+    fn update_chunk(&mut self, chunk: IVec2, dt: f32, fast_forward: bool, commands: &mut Vec<WorldCommand>) -> Result<()> {
+        let Some(keys) = self.map.chunk(chunk).map(|chunk| chunk.coords().collect::<Vec<_>>()) else {return Ok(())};
+        for coords in keys {
+            // Take the machine out of the map while it updates, so it can read its neighbours
+            let Some(mut placed) = self.map.take(&coords) else {continue};
+            // Idle machines don't need their chunk saved again
+            let changed = !placed.machine.is_idle();
+            let mut ctx = UpdateCtx::new(coords, placed.facing, &self.map, commands);
+            let result = if fast_forward {
+                placed.machine.fast_forward(&mut ctx, dt)
+            } else {
+                placed.machine.update(&mut ctx, dt)
+            };
+            self.map.put_back(coords, placed, changed);
+            result?;
+        }
         Ok(())
     }
-    fn draw_background_stars(&mut self, player_cell: Vec2) {
-        rand_with_seed(self.seed);
-        self.star_particles.retain(|(p, v,r, lifetime)| *lifetime <= 10.);
-
-        for n in 0..STAR_PARTICLE_MAX_AMOUNT-self.star_particles.len() as i32 {
-            let x = rand::gen_range(player_cell.x-1., player_cell.x+self.tiles_in_screen().x+1.);
-            let y = rand::gen_range(player_cell.y-1., player_cell.y+self.tiles_in_screen().y+1.);
-            let vx = rand::gen_range(-0.5, 0.5);
-            let vy = rand::gen_range(-0.5, 0.5);
-            let rot = macroquad::rand::gen_range(-360., 360.);
-            let rot_vel = macroquad::rand::gen_range(-1.0, 1.);
-            let lifetime = 0.;
-            self.star_particles.push((vec2(x,y),vec2(vx,vy),vec2(rot,rot_vel), lifetime))
+    /// Catches up on the time since the world was last saved, at most `max_offline` seconds.
+    /// Not done by [`World::load`] itself, so loading stays the same whenever it happens
+    pub fn offline_progress(&mut self, now: u64, max_offline: f64) -> Result<OfflineReport> {
+        // Saves from before the timestamps were stored don't know how long they were away
+        let away = if self.meta.last_played == 0 {0.} else {now.saturating_sub(self.meta.last_played) as f64};
+        let simulated = away.min(max_offline);
+        let before = self.item_totals();
+        let mut remaining = simulated;
+        while remaining > 0. {
+            let dt = remaining.min(simulation::OFFLINE_STEP as f64) as f32;
+            self.energy.solve(&mut self.map, dt);
+            let chunks: Vec<IVec2> = self.map.chunks().map(|(coords, _)| coords).collect();
+            let mut commands = Vec::new();
+            for chunk in chunks {
+                self.update_chunk(chunk, dt, true, &mut commands)?;
+            }
+            self.apply_commands(commands);
+            // A drone picks up or drops off once per step, and a shifter goes through one cell of its path
+            let mut entities_remaining = dt;
+            while entities_remaining > 0. {
+                let entities_dt = entities_remaining.min(simulation::ENTITY_OFFLINE_STEP);
+                let mut commands = Vec::new();
+                self.entities.update(&mut self.map, &mut self.pathfinder, &mut commands, entities_dt)?;
+                self.apply_commands(commands);
+                entities_remaining -= entities_dt;
+            }
+            remaining -= dt as f64;
         }
-        for (pos, vel, rot, lifetime) in &mut self.star_particles {
-            *pos += *vel*get_frame_time();
-            if !Rect::new(player_cell.x-1.,player_cell.y-1., screen_width()/self.tilesize+2., screen_height()/self.tilesize+2.).contains(*pos) {
-                let (x,y) = player_cell.into();
-                let (w,h) = (screen_width()/self.tilesize, screen_height()/self.tilesize);
-                let p = *pos;
-
-                pos.x = if p.x < x-1. {
-                    rand::gen_range(player_cell.x+w-1., player_cell.x+w)
-                } else if p.x > x-1.+w+2. {
-                    rand::gen_range(player_cell.x-1., player_cell.x)
-                } else {pos.x};
-                pos.y = if p.y < y-1. {
-                    rand::gen_range(player_cell.y+h-1., player_cell.y+h)
-                } else if p.y > y-1.+h+2. {
-                    rand::gen_range(player_cell.y-1., player_cell.y)
-                } else {pos.y};
-
+        self.meta.last_played = now;
+        let mut produced: Vec<_> = self.item_totals().into_iter()
+            .filter_map(|(item, amount)| {
+                let amount = amount.saturating_sub(before.get(&item).copied().unwrap_or(0));
+                (amount > 0).then_some(ItemStack { item, amount })
+            })
+            .collect();
+        produced.sort_by_key(|stack| stack.item);
+        Ok(OfflineReport { away, simulated, produced })
+    }
+    /// How many of each item all the machines hold
+    pub fn item_totals(&self) -> hashbrown::HashMap<Item, u32> {
+        let mut totals = hashbrown::HashMap::new();
+        for (_, machine) in self.machines() {
+            for stack in machine.inventory().map(Inventory::contents).unwrap_or_default() {
+                *totals.entry(stack.item).or_default() += stack.amount;
             }
-            let pos = (*pos-player_cell)*self.tilesize;
-            let alpha = (255.0 * (1.0 - ((*lifetime - 5.0) / 5.0).powi(2))) as u8;
-            draw_texture_ex(&self.star_particle, pos.x, pos.y, Color::from_rgba(255, 255, 255, alpha), DrawTextureParams { dest_size: Some(vec2(0.25,0.25)*self.tilesize), rotation: rot.x,..Default::default() });
-            *lifetime += get_frame_time();
-            rot.x += rot.y*get_frame_time();
         }
+        totals
     }
-    pub fn interact(&mut self, player_cell: Vec2, build_mode: &BuildMode) -> Result<()> {
-        if is_key_released(KeyCode::Escape) {
-            self.enabled_gui = None;return Ok(())
+    /// Applies the changes queued during a tick.
+    /// Transfers waiting for space are tried again as long as others make room, so items move along chains in a single tick
+    pub fn apply_commands(&mut self, mut commands: Vec<WorldCommand>) {
+        loop {
+            let count = commands.len();
+            commands = commands.into_iter().filter_map(|command| self.apply_command(command)).collect();
+            if commands.is_empty() || commands.len() == count {break}
         }
-        let rect = if let Some(coords) = self.enabled_gui {
-            let mut m = self.get_tower(&coords);
-            let mut machine = unsafe {Rc::get_mut_unchecked(&mut m)};
-            machine.draw_gui()?
-        } else {Rect::default()};
-        let mp = mouse_position().into();
-        if is_mouse_button_released(MouseButton::Left) && build_mode.current == Tower::Empty && !rect.contains(mp) {
-            let cell = self.screen_to_world(mp, player_cell);
-            if let Some(machine_arc) = self.try_get_tower(&cell) {
-                if self.enabled_gui.is_some() && self.enabled_gui.unwrap() == cell {
-                    self.enabled_gui = None;
-                } else {
-                    self.enabled_gui.replace(cell);
+    }
+    /// Applies a change queued by a machine during the tick, gives it back if it couldn't do anything yet
+    pub fn apply_command(&mut self, command: WorldCommand) -> Option<WorldCommand> {
+        match command {
+            WorldCommand::Transfer { from, to, item, amount } => {
+                let space = self.try_get_tower(&to).map_or(0, |m| m.space_for(item));
+                let taken = match self.map.get_mut(&from) {
+                    Some(machine) => machine.take(item, amount.min(space)),
+                    None => 0,
+                };
+                if taken == 0 {
+                    return (space == 0).then_some(WorldCommand::Transfer { from, to, item, amount })
                 }
-            }
+                let leftover = self.map.get_mut(&to).map_or(taken, |m| m.insert(item, taken));
+                if leftover != 0 {
+                    if let Some(machine) = self.map.get_mut(&from) {
+                        machine.insert(item, leftover);
+                    }
+                }
+            },
+            WorldCommand::SetTower { coords, machine } => {
+                self.set_tower(coords, machine);
+            },
+            WorldCommand::SpawnEntity { pos, behaviour } => {
+                self.entities.spawn(pos, behaviour);
+            },
         }
-        
-        Ok(())
+        None
     }
-    pub fn control_tilesize(&mut self) -> Result<()> {
-        let (wx, mut wy) = mouse_wheel();
-        let zoom_factor = 1.1; 
-
-        if wy > 0.0 { // Scroll up (zoom in)
-            self.tilesize *= zoom_factor;
-        } else if wy < 0.0 { // Scroll down (zoom out)
-            self.tilesize /= zoom_factor;
+    /// Runs the simulation for `ticks` ticks, the player staying at `player_cell`
+    pub fn tick(&mut self, player_cell: Vec2, ticks: u32) -> Result<()> {
+        for _ in 0..ticks {
+            self.update(player_cell, clock::TICK_DT)?;
         }
-
-        let min_tilesize = 3.0;
-        let max_tilesize = 256.0;
-        self.tilesize = self.tilesize.clamp(min_tilesize, max_tilesize);
         Ok(())
     }
-    pub fn remove_gui(&mut self) -> Option<IVec2> {
-        self.enabled_gui.take()
+    pub fn save_name(&self) -> Option<&str> {
+        self.save_name.as_deref()
     }
-
-    pub fn update(&mut self, player_cell: Vec2, dt: f32) -> Result<()> {
-        let keys = {
-            let mut keys = Vec::new();
-            for x in 0..self.update_radius {
-                for y in 0..self.update_radius {
-                    let coords = ivec2(x as i32+player_cell.x as i32-(self.update_radius as i32/2), y as i32+player_cell.y as i32-(self.update_radius as i32/2));
-                    if self.map.contains_key(&coords) {
-                        keys.push(coords);
-                    }
-                }
-            }
-            keys
+    pub fn set_save_name(&mut self, name: impl Into<String>) {
+        self.save_name = Some(name.into());
+    }
+    pub fn save(&mut self) -> Result<()> {
+        let backup_count = unsafe { config::CONFIG.get() }.map_or(config::DEFAULT_BACKUP_COUNT, |config| config.backup_count);
+        self.save_to(&save::files::SaveDir::default(), backup_count)
+    }
+    pub fn save_to(&mut self, dir: &save::files::SaveDir, backup_count: usize) -> Result<()> {
+        let name = match &self.save_name {
+            Some(name) => name.clone(),
+            None => dir.unique_name(&self.meta.name),
         };
-        for coords in keys {
-            let mut machine_rc = self.map.get_mut(&coords).unwrap().clone();
-            // The update function just operates on the tiles, but not on the whole hashmap
-            let mut machine = unsafe {Rc::get_mut_unchecked(&mut machine_rc)};
-            machine.update(&mut self.map, dt)?;
-        }
+        self.meta.last_played = save::meta::now();
+        self.meta.thumbnail = Thumbnail::of(self);
+        dir.write(&name, &self.serialize()?, backup_count)?;
+        self.save_name = Some(name);
         Ok(())
     }
-    pub fn save(&mut self) -> Result<()> {
-        let mut raw = String::new();
-        use std::fmt::Write;
-        writeln!(raw, "Seed = {}", self.seed)?;
-        let mut map = String::new();
-        for (coords, tower) in self.map.iter() {
-            writeln!(map, "    {}: {:?} {{{}}}", coords, tower.ty(), tower.serialize())?;
-        }
-        writeln!(raw, "World = [\n{}]", map)?;
-        if std::fs::exists("saves")? == false {
-            std::fs::create_dir("saves")?
+    /// The state of every machine, serializing only the chunks that changed since the last call
+    pub fn saved_machines(&mut self) -> Result<Vec<save::SavedMachine>> {
+        let chunks: Vec<IVec2> = self.map.chunks().map(|(coords, _)| coords).collect();
+        for coords in chunks {
+            let chunk = self.map.chunk(coords).unwrap();
+            if !chunk.is_dirty() && self.saved_chunks.contains_key(&coords) {continue}
+            let mut saved = Vec::with_capacity(chunk.len());
+            for (coords, placed) in chunk.placed() {
+                let machine = &placed.machine;
+                let state = machine.serialize().with_context(|| format!("Can't save {:?} at {}", machine.ty(), coords))?;
+                saved.push(save::SavedMachine { pos: coords.into(), tower: machine.ty(), facing: placed.facing, state });
+            }
+            self.saved_chunks.insert(coords, saved);
+            self.map.mark_clean(coords);
         }
-        std::fs::write(&format!("saves/{}", self.seed), raw)?;
-        Ok(())
+        Ok(self.saved_chunks.values().flatten().cloned().collect())
+    }
+    pub fn serialize(&mut self) -> Result<String> {
+        save::SaveFile::from_world(self)?.to_string()
     }
-    pub async fn load(raw: String) -> Result<Self> {
-        let mut lines = raw.split("\n");
-        let seed: u64 = lines.next().context("Need seed information for world")?["Seed = ".len()..].parse()?;
-        let world_start = raw.find("World = [\n").context("Need world map")?+"World = [".len();
-        let tower_lines = raw[world_start..].split("\n").skip(1);
-        let mut map = Map::new();
-        fn parse_line(l: &str) -> Result<(IVec2, DynMachine)> {
-            let x_end = l.find(",").context("Can't get x coordinate of machine")?; 
-            let x = l[1..x_end].parse()?; // Skip [
-            let y_end = l.find("]: ").context("Can't get y coordinate of machine")?;
-            let y = l[x_end+2..y_end].parse()?; // Skip ", "
-            let coord = ivec2(x, y);
-            let tower = &l[y_end+3..];
-            let args_start = tower.find(" {").context("Can't get machine args")?;
-            let tower_ty = <Tower as std::str::FromStr>::from_str(&tower[..args_start])?;
-            let tower_args = &tower[args_start+2..tower.len()-1];
-            Ok((coord, tower_ty.deserialize_machine(tower_args)?))
+    pub fn load(raw: String) -> Result<Self> {
+        let save = save::SaveFile::parse(&raw)?;
+        let mut slf = Self::new(save.seed);
+        slf.meta = save.meta.clone();
+        slf.waves = save.waves.clone();
+        for entity in save.entities()? {
+            slf.entities.insert(entity);
         }
-        for l in tower_lines {
-            let l = l.trim();
-            if l.is_empty() {continue}
-            if let Ok((coord, machine)) = parse_line(l) {
-                map.insert(coord, machine);
-            }
+        for (coords, placed) in save.into_machines()? {
+            slf.occupy(coords, placed.machine.ty());
+            slf.map.insert_placed(coords, placed);
         }
-        let mut slf = Self::new(seed).await;
-        slf.map = map;
         Ok(slf)
     }
 }
@@ -1,29 +1,87 @@
+use serde::{Deserialize, Serialize};
+
 use super::*;
+
+/// Scientists drop off a crate of this much antimatter...
+pub const DROPOFF_AMOUNT: u32 = 5;
+/// ...every this many seconds
+pub const DROPOFF_INTERVAL: f32 = 20.;
+
+/// The antimatter dropoff of the first stage: scientists give you a lil bit of antimatter.
+/// A crate only comes once there is room for all of it
+#[derive(Serialize, Deserialize)]
+#[serde(default = "new")]
 pub struct AntimatterCollector {
-    buffer: f32,
+    /// Seconds until the next crate, 0 when it waits for room
+    until_dropoff: f32,
+    inventory: Inventory,
+    /// Antimatter received since the collector was built
+    delivered: u32,
+}
+impl AntimatterCollector {
+    pub fn until_dropoff(&self) -> f32 {self.until_dropoff}
+    pub fn delivered(&self) -> u32 {self.delivered}
+    fn has_room(&self) -> bool {
+        self.inventory.space_for(Item::Antimatter) >= DROPOFF_AMOUNT
+    }
 }
 impl Machine for AntimatterCollector {
-    fn draw_gui(&mut self) -> Result<Rect> {
-        todo!()
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Antimatter dropoff");
+        let (x, y) = (rect.x, rect.y);
+        draw_collect_button(ctx, rect, &mut self.inventory, Item::Antimatter, "antimatter");
+        let status = if self.until_dropoff <= 0. && !self.has_room() {
+            "Next crate: waiting for room".to_string()
+        } else {
+            format!("Next crate of {} in {:.0}s", DROPOFF_AMOUNT, self.until_dropoff.ceil())
+        };
+        draw_text(&status, x+10., y+120., 24., WHITE);
+        draw_rectangle(x+10., y+132., 300., 16., Color::from_rgba(255,255,255,30));
+        draw_rectangle(x+10., y+132., 300.*(1.-self.until_dropoff/DROPOFF_INTERVAL), 16., Color::from_hex(0xc0392b));
+        draw_text(&format!("Delivered so far: {}", self.delivered), x+10., y+176., 24., WHITE);
+        draw_text("Antimatter leaves through the front", x+10., y+204., 24., LIGHTGRAY);
+        Ok(rect)
     }
 
-    fn update(&mut self, map: &mut Map, dt: f32) -> Result<()> {
-        self.buffer += 1.*dt;
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.fast_forward(ctx, dt)
+    }
+
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.until_dropoff -= dt;
+        while self.until_dropoff <= 0. {
+            if !self.has_room() {
+                // The crate waits at the door
+                self.until_dropoff = 0.;
+                break
+            }
+            self.inventory.insert(Item::Antimatter, DROPOFF_AMOUNT);
+            self.delivered += DROPOFF_AMOUNT;
+            self.until_dropoff += DROPOFF_INTERVAL;
+        }
         Ok(())
     }
 
     fn ty(&self) -> Tower {
         Tower::AntimatterCollector
     }
-    
-    fn serialize(&self) -> String {
-        format!("")
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
     }
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
 }
 
 pub fn new() -> AntimatterCollector {
-    AntimatterCollector { buffer: 0. }
+    AntimatterCollector {
+        until_dropoff: DROPOFF_INTERVAL,
+        inventory: Inventory::with_filters(&[Item::Antimatter]),
+        delivered: 0,
+    }
+}
+/// Saves from before the state was kept have an empty one
+pub fn deserialize(state: toml::Table) -> Result<AntimatterCollector> {
+    save::from_state(state)
 }
-pub fn deserialize(raw: &str) -> Result<AntimatterCollector> {
-    Ok(new())
-}
\ No newline at end of file
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+pub const BATTERY_CAPACITY: f32 = 1000.;
+
+/// Stores the surplus of its grid, and gives it back on shortage
+#[derive(Serialize, Deserialize)]
+pub struct Battery {
+    stored: f32,
+}
+impl Battery {
+    pub fn new() -> Self {
+        Self { stored: 0. }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn stored(&self) -> f32 {self.stored}
+}
+impl Machine for Battery {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Battery");
+        draw_text(&format!("Charge: {:.0}/{:.0}", self.stored, BATTERY_CAPACITY), rect.x+10., rect.y+80., 32., WHITE);
+        let bar = Rect::new(rect.x+10., rect.y+100., rect.w-20., 20.);
+        draw_rectangle(bar.x, bar.y, bar.w, bar.h, BLACK);
+        draw_rectangle(bar.x, bar.y, bar.w*self.stored/BATTERY_CAPACITY, bar.h, GREEN);
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::Battery
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn is_idle(&self) -> bool {true}
+
+    fn energy(&self) -> Option<EnergyPort> {
+        Some(EnergyPort::battery(self.stored, BATTERY_CAPACITY))
+    }
+    fn charge(&mut self, energy: f32) {
+        self.stored = (self.stored+energy).clamp(0., BATTERY_CAPACITY);
+    }
+}
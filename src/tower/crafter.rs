@@ -0,0 +1,189 @@
+use recipe::Recipe;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// How many different items a crafter takes in
+pub const CRAFTER_INPUT_SLOTS: usize = 4;
+pub const CRAFTER_OUTPUT_SLOTS: usize = 4;
+/// How many crafts worth of inputs a crafter accepts in advance
+pub const CRAFTER_BUFFERED_CRAFTS: u32 = 5;
+
+/// Makes the selected recipe out of the items given to it, see [`recipe`]
+#[derive(Serialize, Deserialize)]
+pub struct Crafter {
+    #[serde(skip, default)]
+    ty: Tower,
+    /// The name of the selected recipe
+    recipe: Option<String>,
+    /// Progress of the current craft, from 0 to 1
+    progress: f32,
+    inputs: Inventory,
+    output: Inventory,
+    /// Set by the grid every tick
+    #[serde(skip)]
+    power: f32,
+}
+impl Crafter {
+    pub fn new(ty: Tower) -> Self {
+        Self {
+            ty,
+            recipe: None,
+            progress: 0.,
+            inputs: Inventory::new(CRAFTER_INPUT_SLOTS),
+            output: Inventory::new(match ty {
+                Tower::Defined(id) => id.def().inventory,
+                _ => CRAFTER_OUTPUT_SLOTS,
+            }),
+            power: 0.,
+        }
+    }
+    pub fn deserialize(ty: Tower, state: toml::Table) -> Result<Self> {
+        let mut slf: Self = save::from_state(state)?;
+        slf.ty = ty;
+        if let Some(name) = &slf.recipe {
+            if slf.recipe().is_none() {
+                miniquad::warn!("The recipe {:?} of a {} doesn't exist anymore", name, ty.name());
+                slf.recipe = None;
+                slf.progress = 0.;
+            }
+        }
+        Ok(slf)
+    }
+    pub fn recipe(&self) -> Option<&'static Recipe> {
+        recipe::find(self.recipe.as_deref()?).filter(|recipe| recipe.machine == self.ty)
+    }
+    /// Resets the progress, the inputs stay until they are taken out
+    pub fn select(&mut self, recipe: &Recipe) {
+        if self.recipe.as_ref() != Some(&recipe.name) {
+            self.recipe = Some(recipe.name.clone());
+            self.progress = 0.;
+        }
+    }
+    pub fn progress(&self) -> f32 {self.progress}
+    pub fn inputs(&self) -> &Inventory {&self.inputs}
+    /// Whether the inputs of a craft are there and its outputs fit
+    fn can_craft(&self, recipe: &Recipe) -> bool {
+        recipe.inputs.iter().all(|stack| self.inputs.count(stack.item) >= stack.amount)
+            && recipe.outputs.iter().all(|stack| self.output.space_for(stack.item) >= stack.amount)
+    }
+    fn craft(&mut self, recipe: &Recipe) {
+        for stack in &recipe.inputs {
+            self.inputs.take(stack.item, stack.amount);
+        }
+        for stack in &recipe.outputs {
+            self.output.insert(stack.item, stack.amount);
+        }
+    }
+}
+impl Machine for Crafter {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, &self.ty.name());
+        let (x, mut y) = (rect.x+10., rect.y+70.);
+        // Recipe selector
+        for recipe in recipe::for_machine(self.ty) {
+            let selected = self.recipe.as_ref() == Some(&recipe.name);
+            let button = Rect::new(x-5., y-20., rect.w-20., 28.);
+            let alpha = if selected {90} else {30};
+            draw_rectangle(button.x, button.y, button.w, button.h, Color::from_rgba(255,255,255,alpha));
+            draw_text(&format!("{}: {}", recipe.name, recipe.describe()), x, y, 24., WHITE);
+            if clicked_button(button) {
+                self.select(recipe);
+            }
+            y += 32.;
+        }
+        y += 16.;
+        let Some(recipe) = self.recipe() else {
+            draw_text("Select a recipe", x, y, 24., WHITE);
+            return Ok(rect)
+        };
+        let status = if !self.can_craft(recipe) {"waiting for items".to_string()} else {format!("{:.0}% power", self.power*100.)};
+        draw_text(&format!("Progress: {:.0}% ({})", self.progress*100., status), x, y, 24., WHITE);
+        y += 40.;
+        // Hands the player's items over, and gives the products back
+        let fill_rect = Rect::new(x-5., y-28., 200., 40.);
+        let collect_rect = Rect::new(x+215., y-28., 200., 40.);
+        for (button, text) in [(fill_rect, "Fill inputs"), (collect_rect, "Collect")] {
+            draw_rectangle(button.x, button.y, button.w, button.h, Color::from_rgba(255,255,255,30));
+            draw_text(text, button.x+5., y, 32., WHITE);
+        }
+        if clicked_button(fill_rect) {
+            for stack in &recipe.inputs {
+                let space = self.space_for(stack.item);
+                let taken = ctx.player.inventory.take(stack.item, space);
+                let leftover = self.insert(stack.item, taken);
+                ctx.player.inventory.insert(stack.item, leftover);
+            }
+        }
+        if clicked_button(collect_rect) {
+            // Leftovers of previously selected recipes come back too
+            for inventory in [&mut self.output, &mut self.inputs] {
+                for stack in inventory.contents() {
+                    let taken = inventory.take(stack.item, stack.amount);
+                    let leftover = ctx.player.inventory.insert(stack.item, taken);
+                    inventory.insert(stack.item, leftover);
+                }
+            }
+        }
+        y += 40.;
+        for (i, stack) in self.inputs.contents().iter().enumerate() {
+            draw_text(&format!("In: {:?} x{}", stack.item, stack.amount), x, y+28.*i as f32, 24., WHITE);
+        }
+        for (i, stack) in self.output.contents().iter().enumerate() {
+            draw_text(&format!("Out: {:?} x{}", stack.item, stack.amount), x+300., y+28.*i as f32, 24., WHITE);
+        }
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.fast_forward(ctx, dt)
+    }
+
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        let Some(recipe) = self.recipe() else {return Ok(())};
+        if !self.can_craft(recipe) {return Ok(())}
+        // Recipes that don't need energy always run at full speed
+        let speed = if recipe.energy > 0. {self.power} else {1.};
+        self.progress += speed*dt/recipe.duration;
+        while self.progress >= 1. {
+            if !self.can_craft(recipe) {
+                self.progress = 1.;
+                break
+            }
+            self.craft(recipe);
+            self.progress -= 1.;
+        }
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        self.ty
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn energy(&self) -> Option<EnergyPort> {
+        // Only draws power while crafting
+        let demand = self.recipe().filter(|recipe| self.can_craft(recipe)).map_or(0., Recipe::power);
+        Some(EnergyPort::consumer(demand))
+    }
+    fn set_power(&mut self, satisfaction: f32) {
+        self.power = satisfaction;
+    }
+
+    /// The products, the inputs are kept apart
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.output)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.output)}
+    fn space_for(&self, item: Item) -> u32 {
+        let Some(recipe) = self.recipe() else {return 0};
+        let Some(needed) = recipe.inputs.iter().find(|stack| stack.item == item) else {return 0};
+        let buffered = needed.amount*CRAFTER_BUFFERED_CRAFTS;
+        self.inputs.space_for(item).min(buffered.saturating_sub(self.inputs.count(item)))
+    }
+    fn insert(&mut self, item: Item, amount: u32) -> u32 {
+        let accepted = amount.min(self.space_for(item));
+        amount-accepted+self.inputs.insert(item, accepted)
+    }
+}
@@ -0,0 +1,135 @@
+use registry::{Behaviour, TowerDef};
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+fn def_of(ty: Tower) -> &'static TowerDef {
+    match ty {
+        Tower::Defined(id) => id.def(),
+        _ => panic!("{:?} isn't a declared tower", ty),
+    }
+}
+
+/// A declared tower making an item out of nothing, see [`Behaviour::Collector`]
+#[derive(Serialize, Deserialize)]
+pub struct Collector {
+    #[serde(skip, default)]
+    ty: Tower,
+    /// Progress towards the next item, from 0 to 1
+    progress: f32,
+    inventory: Inventory,
+    /// Set by the grid every tick
+    #[serde(skip)]
+    power: f32,
+}
+impl Collector {
+    pub fn new(ty: Tower) -> Self {
+        Self { ty, progress: 0., inventory: Inventory::new(def_of(ty).inventory), power: 0. }
+    }
+    pub fn deserialize(ty: Tower, state: toml::Table) -> Result<Self> {
+        let mut slf: Self = save::from_state(state)?;
+        slf.ty = ty;
+        Ok(slf)
+    }
+    fn collected(&self) -> (Item, f32) {
+        match def_of(self.ty).behaviour {
+            Behaviour::Collector { item, rate } => (item, rate),
+            _ => unreachable!(),
+        }
+    }
+}
+impl Machine for Collector {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let def = def_of(self.ty);
+        let (item, rate) = self.collected();
+        let rect = draw_panel(ctx, &def.name);
+        draw_collect_button(ctx, rect, &mut self.inventory, item, &format!("{:?}", item));
+        let speed = if def.energy > 0. {self.power} else {1.};
+        draw_text(&format!("{:.2} {:?}/s", rate*speed, item), rect.x+10., rect.y+120., 24., WHITE);
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.fast_forward(ctx, dt)
+    }
+
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        let (item, rate) = self.collected();
+        // Towers that don't need energy always run at full speed
+        let speed = if def_of(self.ty).energy > 0. {self.power} else {1.};
+        let produced = self.progress + rate*speed*dt;
+        if self.inventory.insert(item, produced as u32) != 0 {
+            self.progress = 1.;
+        } else {
+            self.progress = produced.fract();
+        }
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        self.ty
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn energy(&self) -> Option<EnergyPort> {
+        let def = def_of(self.ty);
+        if def.energy <= 0. {return None}
+        // Stops drawing power once full
+        let demand = if self.inventory.space_for(self.collected().0) == 0 {0.} else {def.energy};
+        Some(EnergyPort::consumer(demand))
+    }
+    fn set_power(&mut self, satisfaction: f32) {
+        self.power = satisfaction;
+    }
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
+
+/// A declared tower holding items, see [`Behaviour::Storage`]
+#[derive(Serialize, Deserialize)]
+pub struct Storage {
+    #[serde(skip, default)]
+    ty: Tower,
+    inventory: Inventory,
+}
+impl Storage {
+    pub fn new(ty: Tower) -> Self {
+        Self { ty, inventory: Inventory::new(def_of(ty).inventory) }
+    }
+    pub fn deserialize(ty: Tower, state: toml::Table) -> Result<Self> {
+        let mut slf: Self = save::from_state(state)?;
+        slf.ty = ty;
+        Ok(slf)
+    }
+}
+impl Machine for Storage {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, &def_of(self.ty).name);
+        draw_collect_all_button(ctx, rect, &mut self.inventory);
+        for (i, stack) in self.inventory.contents().iter().enumerate() {
+            draw_text(&format!("{:?}: {}", stack.item, stack.amount), rect.x+10., rect.y+120.+28.*i as f32, 24., WHITE);
+        }
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        self.ty
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn is_idle(&self) -> bool {true}
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
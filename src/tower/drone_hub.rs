@@ -0,0 +1,166 @@
+use drone::{Drone, DroneConfig, DroneModule, DRONE_CAPACITY, DRONE_POWER, DRONE_SPEED};
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// How many modules a hub holds
+pub const DRONE_MODULE_SLOTS: usize = 4;
+/// What installing a module costs, given back when it is removed
+pub const DRONE_MODULE_COST: ItemStack = ItemStack { item: Item::String, amount: 20 };
+/// [`Machine::pick_cell`] slots of the hub
+pub const PICKUP_SLOT: usize = 0;
+pub const DROPOFF_SLOT: usize = 1;
+
+/// Home of a drone carrying items from a pickup machine to a dropoff machine, see [`drone::Drone`]
+#[derive(Serialize, Deserialize)]
+pub struct DroneHub {
+    pickup: Option<[i32; 2]>,
+    dropoff: Option<[i32; 2]>,
+    /// At most [`DRONE_MODULE_SLOTS`]
+    modules: Vec<DroneModule>,
+    /// The only item picked up with a [`DroneModule::Filter`]
+    filter: Option<Item>,
+    /// Whether its drone was sent out, the drone is removed along with the hub
+    launched: bool,
+    /// Set by the grid every tick
+    #[serde(skip)]
+    power: f32,
+}
+impl DroneHub {
+    pub fn new() -> Self {
+        Self {
+            pickup: None,
+            dropoff: None,
+            modules: Vec::new(),
+            filter: None,
+            launched: false,
+            power: 0.,
+        }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn modules(&self) -> &[DroneModule] {&self.modules}
+    fn count(&self, module: DroneModule) -> i32 {
+        self.modules.iter().filter(|m| **m == module).count() as i32
+    }
+    /// Puts a module in a free slot with items from `payer`, returns whether it could
+    pub fn install(&mut self, module: DroneModule, payer: &mut Inventory) -> bool {
+        if self.modules.len() >= DRONE_MODULE_SLOTS || !payer.take_exact(DRONE_MODULE_COST.item, DRONE_MODULE_COST.amount) {return false}
+        self.modules.push(module);
+        true
+    }
+    /// Takes the module out of a slot and refunds it to `payer`, if it has room
+    pub fn uninstall(&mut self, slot: usize, payer: &mut Inventory) -> bool {
+        if slot >= self.modules.len() || payer.space_for(DRONE_MODULE_COST.item) < DRONE_MODULE_COST.amount {return false}
+        self.modules.remove(slot);
+        payer.insert(DRONE_MODULE_COST.item, DRONE_MODULE_COST.amount);
+        true
+    }
+    pub fn set_filter(&mut self, filter: Option<Item>) {
+        self.filter = filter;
+    }
+    /// Tiles per second at full power
+    fn speed(&self) -> f32 {
+        DRONE_SPEED*1.5f32.powi(self.count(DroneModule::Speed))
+    }
+    fn capacity(&self) -> u32 {
+        DRONE_CAPACITY+5*self.count(DroneModule::Capacity) as u32
+    }
+    /// Energy per second wanted from the grid
+    fn demand(&self) -> f32 {
+        DRONE_POWER*1.5f32.powi(self.count(DroneModule::Speed))*0.7f32.powi(self.count(DroneModule::Efficiency))
+    }
+}
+impl Machine for DroneHub {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Drone hub");
+        let (x, y) = (rect.x, rect.y);
+        // Targets are picked by clicking on a machine once the panel is closed
+        for (slot, label, target) in [(PICKUP_SLOT, "Pickup", self.pickup), (DROPOFF_SLOT, "Dropoff", self.dropoff)] {
+            let row_y = y+80.+50.*slot as f32;
+            let text = match target {
+                Some([tx, ty]) => format!("{}: {}, {}", label, tx, ty),
+                None => format!("{}: none", label),
+            };
+            draw_text(&text, x+10., row_y, 28., WHITE);
+            let pick_rect = Rect::new(x+500., row_y-28., 80., 40.);
+            draw_rectangle(pick_rect.x, pick_rect.y, pick_rect.w, pick_rect.h, Color::from_rgba(255,255,255,30));
+            draw_text("Pick", pick_rect.x+10., row_y, 28., WHITE);
+            if clicked_button(pick_rect) {
+                ctx.request_pick(slot);
+            }
+        }
+        draw_text(&format!("{:.1} tiles/s, {} items, {:.1} energy/s - grid at {:.0}%", self.speed(), self.capacity(), self.demand(), self.power*100.), x+10., y+190., 24., WHITE);
+        // Clicking a slot takes its module out, the buttons below put one in
+        draw_text(&format!("Modules ({} {:?} each, click to remove)", DRONE_MODULE_COST.amount, DRONE_MODULE_COST.item), x+10., y+230., 24., WHITE);
+        for slot in 0..DRONE_MODULE_SLOTS {
+            let slot_rect = Rect::new(x+10.+130.*slot as f32, y+245., 120., 40.);
+            draw_rectangle(slot_rect.x, slot_rect.y, slot_rect.w, slot_rect.h, Color::from_rgba(255,255,255,30));
+            let text = self.modules.get(slot).map_or("Empty".to_string(), |module| format!("{:?}", module));
+            draw_text(&text, slot_rect.x+8., slot_rect.y+28., 24., WHITE);
+            if clicked_button(slot_rect) {
+                self.uninstall(slot, &mut ctx.player.inventory);
+            }
+        }
+        for (i, module) in DroneModule::iter().enumerate() {
+            let add_rect = Rect::new(x+10.+130.*i as f32, y+295., 120., 32.);
+            draw_rectangle(add_rect.x, add_rect.y, add_rect.w, add_rect.h, Color::from_rgba(255,255,255,15));
+            draw_text(&format!("+ {:?}", module), add_rect.x+8., add_rect.y+24., 22., LIGHTGRAY);
+            if clicked_button(add_rect) {
+                self.install(module, &mut ctx.player.inventory);
+            }
+        }
+        if self.count(DroneModule::Filter) > 0 {
+            let filter_rect = Rect::new(x+5., y+345., 300., 40.);
+            draw_rectangle(filter_rect.x, filter_rect.y, filter_rect.w, filter_rect.h, Color::from_rgba(255,255,255,30));
+            draw_text(&format!("Filter: {}", self.filter.map_or("any item".to_string(), |item| format!("{:?}", item))), x+10., y+373., 28., WHITE);
+            if clicked_button(filter_rect) {
+                // Cycles through every item, then back to none
+                let mut items = Item::iter().skip_while(|item| Some(*item) != self.filter);
+                self.filter = if self.filter.is_none() {Item::iter().next()} else {items.nth(1)};
+            }
+        }
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        if !self.launched {
+            ctx.spawn_entity(Box::new(Drone::new(ctx.coords)));
+            self.launched = true;
+        }
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::DroneHub
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn energy(&self) -> Option<EnergyPort> {
+        Some(EnergyPort::consumer(self.demand()))
+    }
+    fn set_power(&mut self, satisfaction: f32) {
+        self.power = satisfaction;
+    }
+
+    fn drone_config(&self) -> Option<DroneConfig> {
+        Some(DroneConfig {
+            pickup: self.pickup.map(IVec2::from),
+            dropoff: self.dropoff.map(IVec2::from),
+            speed: self.speed()*self.power,
+            capacity: self.capacity(),
+            filter: self.filter.filter(|_| self.count(DroneModule::Filter) > 0),
+        })
+    }
+    fn pick_cell(&mut self, slot: usize, cell: IVec2) {
+        match slot {
+            PICKUP_SLOT => self.pickup = Some(cell.into()),
+            DROPOFF_SLOT => self.dropoff = Some(cell.into()),
+            _ => {},
+        }
+    }
+}
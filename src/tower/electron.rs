@@ -1,59 +1,78 @@
+use serde::{Deserialize, Serialize};
+
 use super::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct Electron {
-    buffer: f32,
+    /// Progress towards the next string, from 0 to 1
+    progress: f32,
     collect_speed: f32,
+    inventory: Inventory,
+    #[serde(skip, default = "default_name")]
     name: String,
 }
+fn default_collect_speed() -> f32 {1.}
+fn default_name() -> String {"Electron".to_string()}
 impl Electron {
     pub fn new() -> Self {
         Self {
-            buffer: 0.,
-            collect_speed: 1.,
-            name: "Electron".to_string(),
+            progress: 0.,
+            collect_speed: default_collect_speed(),
+            inventory: Inventory::with_filters(&[Item::String]),
+            name: default_name(),
         }
     }
-    pub fn deserialize(raw: &str) -> Result<Self> {
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn deserialize_legacy(raw: &str) -> Result<Self> {
         let mut slf = Self::new();
-        slf.buffer = raw["buffer: ".len()..].parse()?;
+        let buffer: f32 = raw.strip_prefix("buffer: ").context("expected `buffer: <f32>`")?.trim().parse()?;
+        slf.inventory.insert(Item::String, buffer as u32);
+        slf.progress = buffer.fract();
         Ok(slf)
     }
 }
 impl Machine for Electron {
-    fn draw_gui(&mut self) -> Result<Rect> {
-        let (x,y) = (100.,50.);
-        let (w,h) = (screen_width()-x*2., screen_height()-y*2.);
-        draw_rectangle(x, y, w, h, DARKGRAY);
-        draw_text(&self.name, x+10., y+32., 32., WHITE);
-        draw_line(x+w-20., y+10., x+w-10., y+20., 2., WHITE);
-        draw_line(x+w-20., y+20., x+w-10., y+10., 2., WHITE);
-        let collect_str = format!("Collect ({:.0} strings)", self.buffer);
-        let collect_rect = Rect::new(x+5., y+80.0-28., collect_str.len() as f32*15., 40.);
-        draw_rectangle(collect_rect.x, collect_rect.y, collect_rect.w, collect_rect.h, Color::from_rgba(255,255,255,30));
-        draw_text(&collect_str, x+10., y+80., 32., WHITE);
-        if is_mouse_button_down(MouseButton::Left) {
-            let mp = mouse_position();
-            if Rect::new(x+w-30., y, 30., 30.).contains(mp.into()) {
-                unsafe { WORLD.as_mut().unwrap().remove_gui().unwrap(); }
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, &self.name);
+        draw_collect_button(ctx, rect, &mut self.inventory, Item::String, "strings");
+        Ok(rect)
+    }
+
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.progress += self.collect_speed * dt;
+        while self.progress >= 1. {
+            if self.inventory.insert(Item::String, 1) != 0 {
+                // Full, wait until someone collects
+                self.progress = 1.;
+                break
             }
+            self.progress -= 1.;
         }
-        if clicked_button(collect_rect) {
-            dbg!();
-        }
-        Ok(Rect::new(x, y, w, h))
+        Ok(())
     }
 
-
-    fn update(&mut self, map: &mut Map, dt: f32) -> Result<()> {
-        self.buffer += self.collect_speed * dt;
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        // Steady rate until full, no need to go item by item
+        let produced = self.progress + self.collect_speed*dt;
+        if self.inventory.insert(Item::String, produced as u32) != 0 {
+            self.progress = 1.;
+        } else {
+            self.progress = produced.fract();
+        }
         Ok(())
     }
 
     fn ty(&self) -> Tower {
         Tower::Electron
     }
-    
-    fn serialize(&self) -> String {
-        format!("buffer: {}", self.buffer)
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
     }
-}
\ No newline at end of file
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// How many items fit before any upgrade
+pub const ITEM_DEPOSIT_CAPACITY: u32 = 500;
+/// Items added by each capacity upgrade
+pub const ITEM_DEPOSIT_CAPACITY_PER_LEVEL: u32 = 500;
+
+/// Fast to put items in and take them out, but only holds one type of item at a time
+#[derive(Serialize, Deserialize)]
+pub struct ItemDeposit {
+    /// A single slot, ignoring stack sizes
+    inventory: Inventory,
+    /// Capacity upgrades bought, see [`MAX_STORAGE_LEVEL`]
+    #[serde(default)]
+    level: u32,
+}
+impl ItemDeposit {
+    pub fn new() -> Self {
+        Self {
+            inventory: Inventory::new(1).with_stack_limit(ITEM_DEPOSIT_CAPACITY),
+            level: 0,
+        }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn level(&self) -> u32 {self.level}
+    pub fn capacity(&self) -> u32 {
+        ITEM_DEPOSIT_CAPACITY+self.level*ITEM_DEPOSIT_CAPACITY_PER_LEVEL
+    }
+    /// Buys the next capacity upgrade with items from `payer`, returns whether it could
+    pub fn try_upgrade(&mut self, payer: &mut Inventory) -> bool {
+        let cost = storage_upgrade_cost(self.level);
+        if self.level >= MAX_STORAGE_LEVEL || !payer.take_exact(cost.item, cost.amount) {return false}
+        self.level += 1;
+        self.inventory = std::mem::take(&mut self.inventory).with_stack_limit(self.capacity());
+        true
+    }
+}
+impl Machine for ItemDeposit {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Item deposit");
+        draw_collect_all_button(ctx, rect, &mut self.inventory);
+        if draw_upgrade_button(rect.x+230., rect.y+80., self.level) {
+            self.try_upgrade(&mut ctx.player.inventory);
+        }
+        let contents = match self.inventory.contents().first() {
+            Some(stack) => format!("{:?}: {}/{}", stack.item, stack.amount, self.capacity()),
+            None => format!("Empty, takes any item: 0/{}", self.capacity()),
+        };
+        draw_text(&contents, rect.x+10., rect.y+120., 24., WHITE);
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::ItemDeposit
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn is_idle(&self) -> bool {true}
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
@@ -1,45 +1,181 @@
-use color_eyre::eyre::ContextCompat;
+use color_eyre::eyre::{eyre, ContextCompat};
 use macroquad::ui::root_ui;
 use strum::{EnumCount, EnumProperty, IntoEnumIterator};
 use tiles::{new_machine, DynMachine, Map, WORLD};
+use item::{Inventory, Item, ItemStack};
+use player::Player;
+use energy::{EnergyPort, GridStats};
+use direction::{Direction, Side};
+use drone::DroneConfig;
 
 use super::*;
 
 pub mod electron;
 pub mod string_creator;
 pub mod antimatter_collector;
+pub mod vacuum_collector;
+pub mod power_node;
+pub mod battery;
+pub mod photon_emitter;
+pub mod transport;
+pub mod unsorted_deposit;
+pub mod item_deposit;
+pub mod crafter;
+pub mod registry;
+pub mod defined;
+pub mod drone_hub;
 
 use std::{borrow::Borrow, cell::RefCell, sync::RwLock};
 
 pub static TOWER_TEXTURES: [RwLock<Option<Texture2D>>; Tower::COUNT] = [const{RwLock::new(None)}; Tower::COUNT];
 
 pub async fn setup_cache_tower_textures() -> Result<()> {
-    if let Err(e) = EMPTY_MACHINE.with(|cell| cell.set(new_machine(EmptyMachine {}))) {
-        todo!()
-    }
-    for (i,tower) in Tower::iter().enumerate() {
-        TOWER_TEXTURES[i].write().unwrap().replace(tower.load_texture().await?);
+    for tower in registry::all() {
+        let texture = tower.load_texture().await?;
+        match tower {
+            Tower::Defined(id) => registry::set_texture(id, texture),
+            _ => {TOWER_TEXTURES[tower.index()].write().unwrap().replace(texture);},
+        }
     }
     
     Ok(())
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::EnumIter, strum_macros::EnumCount, strum_macros::EnumProperty, strum_macros::EnumString)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum_macros::EnumIter, strum_macros::EnumCount, strum_macros::EnumProperty, strum_macros::EnumString)]
 pub enum Tower {
+    #[default]
     #[strum(props(asset_path = "empty.png"))]
     Empty,
-    #[strum(props(asset_path = "electron.png", buildable = "true"))]
+    #[strum(props(asset_path = "electron.png", buildable = "true", map_color = "3fa9f5", outputs = "all"))]
     Electron,
-    // #[strum(props(asset_path = "string creator.png", buildable = "true"))]
-    // StringCreator,
-    #[strum(props(asset_path = "antimatter_collector.png", buildable = "true"))]
+    #[strum(props(asset_path = "string creator.png", buildable = "true", map_color = "b084cc", outputs = "all"))]
+    StringCreator,
+    #[strum(props(asset_path = "antimatter_collector.png", buildable = "true", map_color = "c0392b", outputs = "front"))]
     AntimatterCollector,
+    #[strum(props(asset_path = "energy.png", buildable = "true", map_color = "f1c40f"))]
+    VacuumCollector,
+    #[strum(props(asset_path = "power node.png", buildable = "true", map_color = "95a5a6"))]
+    PowerNode,
+    #[strum(props(asset_path = "battery.png", buildable = "true", map_color = "27ae60", size = "2"))]
+    Battery,
+    #[strum(props(asset_path = "photon emitter.png", buildable = "true", map_color = "fff3a0", outputs = "all"))]
+    PhotonEmitter,
+    #[strum(props(asset_path = "conveyor.png", buildable = "true", map_color = "4a4a55", inputs = "back,left,right", outputs = "front"))]
+    Conveyor,
+    #[strum(props(asset_path = "item pipe.png", buildable = "true", map_color = "8c9db0", inputs = "back", outputs = "front"))]
+    ItemPipe,
+    #[strum(props(asset_path = "unsorted deposit.png", buildable = "true", map_color = "8b5a2b", inputs = "all", outputs = "all"))]
+    UnsortedDeposit,
+    #[strum(props(asset_path = "item deposit.png", buildable = "true", map_color = "a0522d", inputs = "all", outputs = "all"))]
+    ItemDeposit,
+    #[strum(props(asset_path = "string reshaper.png", buildable = "true", crafter = "true", map_color = "9b59b6", inputs = "all", outputs = "all"))]
+    StringReshaper,
+    #[strum(props(asset_path = "baryonic combinator.png", buildable = "true", crafter = "true", map_color = "e67e22", inputs = "all", outputs = "all"))]
+    BaryonicCombinator,
+    #[strum(props(asset_path = "nucleus fusion reactor.png", buildable = "true", crafter = "true", map_color = "f39c12", inputs = "all", outputs = "all", size = "2"))]
+    NucleusFusionReactor,
+    #[strum(props(asset_path = "ion fusion reactor.png", buildable = "true", crafter = "true", map_color = "1abc9c", inputs = "all", outputs = "all", size = "2"))]
+    IonFusionReactor,
+    #[strum(props(asset_path = "drone hub.png", buildable = "true", map_color = "5dade2"))]
+    DroneHub,
+    /// Declared in a TOML file, see [`registry`]
+    #[strum(disabled)]
+    Defined(registry::TowerId),
+}
+/// Saves refer to towers by name, see [`Tower::name`]
+impl serde::Serialize for Tower {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+impl<'de> serde::Deserialize<'de> for Tower {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Tower::named(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown tower {:?}", name)))
+    }
 }
-impl Default for Tower {fn default() -> Self {Self::Empty}}
+/// The biggest [`Tower::size`], so drawing knows how far off screen an anchor can be
+pub const MAX_TOWER_SIZE: i32 = 3;
+
 impl Tower {
+    /// A tower written in Rust or declared in [`registry::TOWERS_DIR`]
+    pub fn named(name: &str) -> Option<Self> {
+        name.parse().ok().or_else(|| registry::find(name))
+    }
+    pub fn name(self) -> String {
+        match self {
+            Tower::Defined(id) => id.def().name.clone(),
+            _ => format!("{:?}", self),
+        }
+    }
+    /// Position among the towers written in Rust
+    fn index(self) -> usize {
+        Tower::iter().position(|tower| tower == self).unwrap()
+    }
     pub fn texture_path(self) -> &'static str {
-        self.get_str("asset_path").unwrap_or(Self::default().get_str("asset_path").unwrap())
-    } 
+        match self {
+            Tower::Defined(id) => &id.def().texture,
+            _ => self.get_str("asset_path").unwrap_or(Self::default().get_str("asset_path").unwrap()),
+        }
+    }
+    /// The color of the tower on thumbnails
+    pub fn map_color(self) -> Color {
+        let hex = match self {
+            Tower::Defined(id) => id.def().map_color.as_deref(),
+            _ => self.get_str("map_color"),
+        };
+        hex.and_then(|hex| u32::from_str_radix(hex, 16).ok()).map_or(GRAY, Color::from_hex)
+    }
+    /// Whether the player can place it from the hotbar
+    pub fn is_buildable(self) -> bool {
+        match self {
+            Tower::Defined(id) => id.def().buildable,
+            _ => self.get_str("buildable") == Some("true"),
+        }
+    }
+    /// Whether the tower is a [`crafter::Crafter`], running the recipes made for it
+    pub fn is_crafter(self) -> bool {
+        match self {
+            Tower::Defined(id) => id.def().behaviour == registry::Behaviour::Crafter,
+            _ => self.get_str("crafter") == Some("true"),
+        }
+    }
+    /// How many tiles wide and high the tower is, see [`Tower::footprint`]
+    pub fn size(self) -> i32 {
+        match self {
+            Tower::Defined(id) => id.def().size,
+            _ => self.get_str("size").and_then(|size| size.parse().ok()).unwrap_or(1),
+        }
+    }
+    /// Every cell covered by the tower when its anchor, the top left cell, is at `anchor`
+    pub fn footprint(self, anchor: IVec2) -> impl Iterator<Item = IVec2> {
+        let size = self.size();
+        (0..size).flat_map(move |y| (0..size).map(move |x| anchor+ivec2(x, y)))
+    }
+    /// The sides, relative to where the tower faces, that take items in
+    pub fn inputs(self) -> impl Iterator<Item = Side> {
+        let list = match self {
+            Tower::Defined(id) => &id.def().inputs,
+            _ => self.get_str("inputs").unwrap_or_default(),
+        };
+        Side::parse_list(list)
+    }
+    /// The sides, relative to where the tower faces, that hand items out
+    pub fn outputs(self) -> impl Iterator<Item = Side> {
+        let list = match self {
+            Tower::Defined(id) => &id.def().outputs,
+            _ => self.get_str("outputs").unwrap_or_default(),
+        };
+        Side::parse_list(list)
+    }
+    /// Whether the tower, placed facing `facing`, takes items coming from the `from` direction
+    pub fn accepts_from(self, facing: Direction, from: Direction) -> bool {
+        self.inputs().any(|side| facing.turned(side) == from)
+    }
+    /// Whether the tower, placed facing `facing`, hands items out towards the `to` direction
+    pub fn outputs_to(self, facing: Direction, to: Direction) -> bool {
+        self.outputs().any(|side| facing.turned(side) == to)
+    }
     /// loads a new texture, expensive
     pub async fn load_texture(self) -> Result<Texture2D> {
         let texture = match load_texture(&format!("assets/{}", self.texture_path())).await {
@@ -52,7 +188,10 @@ impl Tower {
         Ok(texture)
     }
     pub fn try_loaded_texture(self) -> Option<Texture2D> {
-        Some(TOWER_TEXTURES[self as usize].read().ok()?.clone()?)
+        match self {
+            Tower::Defined(id) => registry::texture(id),
+            _ => TOWER_TEXTURES[self.index()].read().ok()?.clone(),
+        }
     }
     pub async fn loaded_texture(self) -> Texture2D {
         if let Some(texture) = self.try_loaded_texture() {
@@ -66,29 +205,286 @@ impl Tower {
         Some(match self {
             Tower::Empty    => new_machine(EmptyMachine {}),
             Tower::Electron => new_machine(electron::Electron::new()),
-            // Tower::StringCreator => new_machine(string_creator::StringCreator::new()),
+            Tower::StringCreator => new_machine(string_creator::StringCreator::new()),
             Tower::AntimatterCollector => new_machine(antimatter_collector::new()),
+            Tower::VacuumCollector => new_machine(vacuum_collector::VacuumCollector::new()),
+            Tower::PowerNode => new_machine(power_node::PowerNode::new()),
+            Tower::Battery => new_machine(battery::Battery::new()),
+            Tower::PhotonEmitter => new_machine(photon_emitter::PhotonEmitter::new()),
+            Tower::Conveyor | Tower::ItemPipe => new_machine(transport::Transport::new(self)),
+            Tower::UnsortedDeposit => new_machine(unsorted_deposit::UnsortedDeposit::new()),
+            Tower::ItemDeposit => new_machine(item_deposit::ItemDeposit::new()),
+            Tower::DroneHub => new_machine(drone_hub::DroneHub::new()),
+            Tower::StringReshaper | Tower::BaryonicCombinator | Tower::NucleusFusionReactor | Tower::IonFusionReactor => new_machine(crafter::Crafter::new(self)),
+            Tower::Defined(id) => match id.def().behaviour {
+                registry::Behaviour::Collector { .. } => new_machine(defined::Collector::new(self)),
+                registry::Behaviour::Crafter => new_machine(crafter::Crafter::new(self)),
+                registry::Behaviour::Storage => new_machine(defined::Storage::new(self)),
+            },
         })
     }
-    pub fn deserialize_machine(self, raw: &str) -> Result<DynMachine> {
+    /// Rebuilds a machine from the state returned by [`Machine::serialize`]
+    pub fn deserialize_machine(self, state: toml::Table) -> Result<DynMachine> {
         Ok(match self {
             Tower::Empty    => new_machine(EmptyMachine {}),
-            Tower::Electron => new_machine(electron::Electron::deserialize(raw)?),
-            // Tower::StringCreator => new_machine(string_creator::StringCreator::deserialize(raw)),
-            Tower::AntimatterCollector => new_machine(antimatter_collector::deserialize(raw)?),
+            Tower::Electron => new_machine(electron::Electron::deserialize(state)?),
+            Tower::StringCreator => new_machine(string_creator::StringCreator::deserialize(state)?),
+            Tower::AntimatterCollector => new_machine(antimatter_collector::deserialize(state)?),
+            Tower::VacuumCollector => new_machine(vacuum_collector::VacuumCollector::deserialize(state)?),
+            Tower::PowerNode => new_machine(power_node::PowerNode::deserialize(state)?),
+            Tower::Battery => new_machine(battery::Battery::deserialize(state)?),
+            Tower::PhotonEmitter => new_machine(photon_emitter::PhotonEmitter::deserialize(state)?),
+            Tower::Conveyor | Tower::ItemPipe => new_machine(transport::Transport::deserialize(self, state)?),
+            Tower::UnsortedDeposit => new_machine(unsorted_deposit::UnsortedDeposit::deserialize(state)?),
+            Tower::ItemDeposit => new_machine(item_deposit::ItemDeposit::deserialize(state)?),
+            Tower::DroneHub => new_machine(drone_hub::DroneHub::deserialize(state)?),
+            Tower::StringReshaper | Tower::BaryonicCombinator | Tower::NucleusFusionReactor | Tower::IonFusionReactor => new_machine(crafter::Crafter::deserialize(self, state)?),
+            Tower::Defined(id) => match id.def().behaviour {
+                registry::Behaviour::Collector { .. } => new_machine(defined::Collector::deserialize(self, state)?),
+                registry::Behaviour::Crafter => new_machine(crafter::Crafter::deserialize(self, state)?),
+                registry::Behaviour::Storage => new_machine(defined::Storage::deserialize(self, state)?),
+            },
         })
     }
+    /// Rebuilds a machine from its arguments in a legacy save, see [`save::legacy`]
+    pub fn deserialize_legacy_machine(self, raw: &str) -> Result<DynMachine> {
+        Ok(match self {
+            Tower::Empty    => new_machine(EmptyMachine {}),
+            Tower::Electron => new_machine(electron::Electron::deserialize_legacy(raw)?),
+            Tower::AntimatterCollector => new_machine(antimatter_collector::new()),
+            _ => return Err(eyre!("{:?} didn't exist in legacy saves", self)),
+        })
+    }
+}
+
+/// What a machine's GUI can interact with outside of the machine itself
+pub struct GuiCtx<'a> {
+    pub player: &'a mut Player,
+    /// The power grid the machine is part of
+    pub grid: Option<GridStats>,
+    close: bool,
+    pick: Option<usize>,
+}
+impl<'a> GuiCtx<'a> {
+    pub fn new(player: &'a mut Player) -> Self {
+        Self { player, grid: None, close: false, pick: None }
+    }
+    pub fn with_grid(mut self, grid: Option<GridStats>) -> Self {
+        self.grid = grid;
+        self
+    }
+    /// Closes the GUI once the machine is done drawing it
+    pub fn close(&mut self) {
+        self.close = true;
+    }
+    pub fn close_requested(&self) -> bool {self.close}
+    /// Closes the GUI and lets the player click on a machine, given to [`Machine::pick_cell`] with this slot
+    pub fn request_pick(&mut self, slot: usize) {
+        self.pick = Some(slot);
+        self.close = true;
+    }
+    pub fn pick_requested(&self) -> Option<usize> {self.pick}
+}
+
+/// Draws the window shared by machine GUIs: background, title, close button and grid stats. Returns its rect
+pub fn draw_panel(ctx: &mut GuiCtx, title: &str) -> Rect {
+    let (x,y) = (100.,50.);
+    let (w,h) = (screen_width()-x*2., screen_height()-y*2.);
+    draw_rectangle(x, y, w, h, DARKGRAY);
+    draw_text(title, x+10., y+32., 32., WHITE);
+    draw_line(x+w-20., y+10., x+w-10., y+20., 2., WHITE);
+    draw_line(x+w-20., y+20., x+w-10., y+10., 2., WHITE);
+    if is_mouse_button_down(MouseButton::Left) {
+        let mp = mouse_position();
+        if Rect::new(x+w-30., y, 30., 30.).contains(mp.into()) {
+            ctx.close();
+        }
+    }
+    if let Some(grid) = ctx.grid {
+        grid.draw(x+10., y+h-24.*4.);
+    }
+    Rect::new(x, y, w, h)
+}
+
+/// Draws the button under the title moving every `item` of `inventory` to the player, `name` is what the item is called on it
+pub fn draw_collect_button(ctx: &mut GuiCtx, panel: Rect, inventory: &mut Inventory, item: Item, name: &str) {
+    let text = format!("Collect ({} {})", inventory.count(item), name);
+    let rect = Rect::new(panel.x+5., panel.y+80.0-28., text.len() as f32*15., 40.);
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::from_rgba(255,255,255,30));
+    draw_text(&text, panel.x+10., panel.y+80., 32., WHITE);
+    if clicked_button(rect) {
+        collect(ctx, inventory, item, u32::MAX);
+    }
+}
+/// Draws the button under the title moving everything in `inventory` to the player
+pub fn draw_collect_all_button(ctx: &mut GuiCtx, panel: Rect, inventory: &mut Inventory) {
+    let rect = Rect::new(panel.x+5., panel.y+80.0-28., 200., 40.);
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::from_rgba(255,255,255,30));
+    draw_text("Collect all", panel.x+10., panel.y+80., 32., WHITE);
+    if clicked_button(rect) {
+        for stack in inventory.contents() {
+            collect(ctx, inventory, stack.item, stack.amount);
+        }
+    }
+}
+/// Moves items to the player, what doesn't fit stays in `inventory`
+fn collect(ctx: &mut GuiCtx, inventory: &mut Inventory, item: Item, amount: u32) {
+    let taken = inventory.take(item, amount);
+    let leftover = ctx.player.inventory.insert(item, taken);
+    inventory.insert(item, leftover);
+}
+
+/// How many times the capacity of storage towers can be upgraded
+pub const MAX_STORAGE_LEVEL: u32 = 3;
+/// What upgrading a storage tower from `level` costs
+pub fn storage_upgrade_cost(level: u32) -> ItemStack {
+    ItemStack::new(Item::String, 50*(level+1))
+}
+/// Draws the capacity upgrade button of storage towers, returns whether it was clicked
+pub fn draw_upgrade_button(x: f32, y: f32, level: u32) -> bool {
+    let text = if level >= MAX_STORAGE_LEVEL {
+        format!("Level {} (max)", level)
+    } else {
+        let cost = storage_upgrade_cost(level);
+        format!("Upgrade to level {} ({} {:?})", level+1, cost.amount, cost.item)
+    };
+    let rect = Rect::new(x-5., y-28., text.len() as f32*13., 40.);
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::from_rgba(255,255,255,30));
+    draw_text(&text, x, y, 28., WHITE);
+    level < MAX_STORAGE_LEVEL && clicked_button(rect)
+}
+
+/// The 4 cells sharing a side with a machine
+pub const NEIGHBOURS: [IVec2; 4] = [IVec2::NEG_Y, IVec2::X, IVec2::Y, IVec2::NEG_X];
+
+/// A change a machine wants to make outside of itself, applied once every machine has updated
+pub enum WorldCommand {
+    /// Moves up to `amount` items, only as many as `to` can accept
+    Transfer { from: IVec2, to: IVec2, item: Item, amount: u32 },
+    SetTower { coords: IVec2, machine: DynMachine },
+    /// Adds an entity to the world at `pos`, in tiles
+    SpawnEntity { pos: Vec2, behaviour: Box<dyn entity::Behaviour> },
+}
+
+/// What a machine sees of the world while it updates
+pub struct UpdateCtx<'a> {
+    /// The coordinates of the machine being updated
+    pub coords: IVec2,
+    /// Where the machine being updated faces
+    pub facing: Direction,
+    map: &'a Map,
+    commands: &'a mut Vec<WorldCommand>,
+}
+impl<'a> UpdateCtx<'a> {
+    pub fn new(coords: IVec2, facing: Direction, map: &'a Map, commands: &'a mut Vec<WorldCommand>) -> Self {
+        Self { coords, facing, map, commands }
+    }
+    pub fn facing_of(&self, coords: IVec2) -> Option<Direction> {
+        self.map.facing(&coords)
+    }
+    /// The coordinates of the tile on a side of the machine being updated
+    pub fn side(&self, side: Side) -> IVec2 {
+        self.coords+self.facing.turned(side).offset()
+    }
+    /// The machine on a side, if it hands items out towards the machine being updated
+    pub fn source(&self, side: Side) -> Option<(IVec2, &dyn Machine)> {
+        let direction = self.facing.turned(side);
+        let coords = self.coords+direction.offset();
+        let placed = self.map.get_placed(&coords)?;
+        placed.machine.ty().outputs_to(placed.facing, direction.opposite()).then_some((coords, &*placed.machine))
+    }
+    /// The machine on a side, if it takes items in from the machine being updated
+    pub fn target(&self, side: Side) -> Option<(IVec2, &dyn Machine)> {
+        let direction = self.facing.turned(side);
+        let coords = self.coords+direction.offset();
+        let placed = self.map.get_placed(&coords)?;
+        placed.machine.ty().accepts_from(placed.facing, direction.opposite()).then_some((coords, &*placed.machine))
+    }
+    /// The machine at the given coordinates, the machine being updated can't see itself
+    pub fn get(&self, coords: IVec2) -> Option<&dyn Machine> {
+        self.map.get(&coords).map(|machine| &**machine)
+    }
+    /// The machine at an offset from the one being updated
+    pub fn neighbour(&self, offset: IVec2) -> Option<&dyn Machine> {
+        self.get(self.coords+offset)
+    }
+    /// The adjacent machines with their coordinates
+    pub fn neighbours(&self) -> impl Iterator<Item = (IVec2, &dyn Machine)> {
+        NEIGHBOURS.into_iter().filter_map(|offset| {
+            let coords = self.coords+offset;
+            self.get(coords).map(|machine| (coords, machine))
+        })
+    }
+    pub fn transfer(&mut self, from: IVec2, to: IVec2, item: Item, amount: u32) {
+        self.commands.push(WorldCommand::Transfer { from, to, item, amount });
+    }
+    pub fn set_tower(&mut self, coords: IVec2, machine: DynMachine) {
+        self.commands.push(WorldCommand::SetTower { coords, machine });
+    }
+    pub fn remove_tower(&mut self, coords: IVec2) {
+        self.set_tower(coords, new_machine(EmptyMachine {}));
+    }
+    /// Adds an entity over the center of the machine being updated
+    pub fn spawn_entity(&mut self, behaviour: Box<dyn entity::Behaviour>) {
+        let pos = vec2i_to_f(self.coords)+Vec2::splat(0.5);
+        self.commands.push(WorldCommand::SpawnEntity { pos, behaviour });
+    }
 }
 
 pub trait Machine {
-    fn draw_gui(&mut self) -> Result<Rect>;
-    fn update(&mut self, map: &mut Map, dt: f32) -> Result<()>;
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect>;
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()>;
+    /// Simulates a long duration at once, for machines far from the player.
+    /// Machines should compute what they would do at their steady-state throughput, by default it's a single big update
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.update(ctx, dt)
+    }
     fn ty(&self) -> Tower;
-    fn serialize(&self) -> String;
+    /// The state needed to rebuild the machine with [`Tower::deserialize_machine`]
+    fn serialize(&self) -> Result<toml::Table>;
+    /// True when updating can't change what [`Machine::serialize`] gives, so updates don't make its chunk saved again
+    fn is_idle(&self) -> bool {false}
     #[track_caller]
     fn texture(&self) -> Texture2D {
         self.ty().try_loaded_texture().context(format!("Can't get texture of {:?}", self.ty())).unwrap()
     }
+    /// Draws on top of the machine's texture, e.g. the items it carries. `pos` is the top left corner on screen
+    fn draw_overlay(&self, pos: Vec2, tilesize: f32, facing: Direction) {}
+    /// How the machine takes part in power grids, None if it doesn't use energy
+    fn energy(&self) -> Option<EnergyPort> {None}
+    /// Called every tick with the fraction (0 to 1) of the machine's demand its grid could meet
+    fn set_power(&mut self, satisfaction: f32) {}
+    /// Stores energy in the machine, or takes some out when negative
+    fn charge(&mut self, energy: f32) {}
+    /// The items stored in the machine, if it can store any
+    fn inventory(&self) -> Option<&Inventory> {None}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {None}
+    /// How many items of this type the machine can still accept
+    fn space_for(&self, item: Item) -> u32 {
+        self.inventory().map_or(0, |inv| inv.space_for(item))
+    }
+    /// Gives items to the machine, returns how many it couldn't accept
+    fn insert(&mut self, item: Item, amount: u32) -> u32 {
+        match self.inventory_mut() {
+            Some(inv) => inv.insert(item, amount),
+            None => amount,
+        }
+    }
+    /// Takes items out of the machine, returns how many were handed out
+    fn take(&mut self, item: Item, amount: u32) -> u32 {
+        match self.inventory_mut() {
+            Some(inv) => inv.take(item, amount),
+            None => 0,
+        }
+    }
+    /// The items the machine is ready to hand out
+    fn outputs(&self) -> Vec<ItemStack> {
+        self.inventory().map(Inventory::contents).unwrap_or_default()
+    }
+    /// What the drones living in this machine should do, None if it isn't a drone home
+    fn drone_config(&self) -> Option<DroneConfig> {None}
+    /// The player clicked on `cell` after the GUI asked for it with [`GuiCtx::request_pick`]
+    fn pick_cell(&mut self, slot: usize, cell: IVec2) {}
 }
 
 
@@ -99,19 +495,21 @@ pub struct EmptyMachine {
 
 }
 impl Machine for EmptyMachine {
-    fn draw_gui(&mut self) -> Result<Rect> {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
         unreachable!()
     }
 
-    fn update(&mut self, map: &mut Map, dt: f32) -> Result<()> {
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
         Ok(())
     }
 
     fn ty(&self) -> Tower {
         Tower::Empty
     }
+
+    fn is_idle(&self) -> bool {true}
     
-    fn serialize(&self) -> String {
+    fn serialize(&self) -> Result<toml::Table> {
         unreachable!()
     }
 }
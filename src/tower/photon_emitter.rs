@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Photons per second at full power
+pub const PHOTON_RATE: f32 = 1.;
+pub const ENERGY_PER_PHOTON: f32 = 4.;
+
+/// Turns energy into photons, slows down when its grid can't keep up
+#[derive(Serialize, Deserialize)]
+pub struct PhotonEmitter {
+    /// Progress towards the next photon, from 0 to 1
+    progress: f32,
+    inventory: Inventory,
+    /// Set by the grid every tick
+    #[serde(skip)]
+    power: f32,
+}
+impl PhotonEmitter {
+    pub fn new() -> Self {
+        Self {
+            progress: 0.,
+            inventory: Inventory::with_filters(&[Item::Photon]),
+            power: 0.,
+        }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+}
+impl Machine for PhotonEmitter {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Photon emitter");
+        draw_collect_button(ctx, rect, &mut self.inventory, Item::Photon, "photons");
+        draw_text(&format!("Running at {:.0}%", self.power*100.), rect.x+10., rect.y+120., 24., WHITE);
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.fast_forward(ctx, dt)
+    }
+
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        let produced = self.progress + PHOTON_RATE*self.power*dt;
+        if self.inventory.insert(Item::Photon, produced as u32) != 0 {
+            self.progress = 1.;
+        } else {
+            self.progress = produced.fract();
+        }
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::PhotonEmitter
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn energy(&self) -> Option<EnergyPort> {
+        // Stops drawing power once full
+        let demand = if self.inventory.space_for(Item::Photon) == 0 {0.} else {PHOTON_RATE*ENERGY_PER_PHOTON};
+        Some(EnergyPort::consumer(demand))
+    }
+    fn set_power(&mut self, satisfaction: f32) {
+        self.power = satisfaction;
+    }
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// How far a power node reaches other machines, in tiles
+pub const POWER_NODE_RANGE: i32 = 5;
+
+/// Joins every machine using energy around it into the same grid
+#[derive(Serialize, Deserialize)]
+pub struct PowerNode {}
+impl PowerNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+}
+impl Machine for PowerNode {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Power node");
+        draw_text(&format!("Connects machines up to {} tiles away", POWER_NODE_RANGE), rect.x+10., rect.y+80., 32., WHITE);
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::PowerNode
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn is_idle(&self) -> bool {true}
+
+    fn energy(&self) -> Option<EnergyPort> {
+        Some(EnergyPort::connector(POWER_NODE_RANGE))
+    }
+}
@@ -0,0 +1,170 @@
+use std::{path::Path, str::FromStr, sync::{OnceLock, RwLock}};
+
+use color_eyre::eyre::{bail, eyre, Context};
+use hashbrown::HashSet;
+use serde::Deserialize;
+
+use super::*;
+
+pub const TOWERS_DIR: &str = "assets/towers";
+
+/// The towers declared in [`TOWERS_DIR`], loaded once
+static DEFINITIONS: OnceLock<Vec<TowerDef>> = OnceLock::new();
+/// Textures of the declared towers, by [`TowerId`]
+static TEXTURES: RwLock<Vec<Option<Texture2D>>> = RwLock::new(Vec::new());
+
+/// A tower declared in a TOML file, see [`Tower::Defined`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TowerId(u16);
+impl TowerId {
+    pub fn def(self) -> &'static TowerDef {
+        &definitions()[self.0 as usize]
+    }
+}
+
+/// What a declared tower does
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Behaviour {
+    /// Makes `item` out of nothing, `rate` per second
+    Collector { item: Item, rate: f32 },
+    /// Runs the recipes made for it, see [`recipe`]
+    Crafter,
+    /// Holds items
+    Storage,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TowerDef {
+    /// Unique, also among the towers written in Rust. Saves refer to towers by name
+    pub name: String,
+    /// Relative to the assets folder
+    pub texture: String,
+    /// On thumbnails, as hex
+    #[serde(default)]
+    pub map_color: Option<String>,
+    /// Width and height in tiles
+    #[serde(default = "default_size")]
+    pub size: i32,
+    #[serde(default)]
+    pub buildable: bool,
+    /// Energy per second used while working, crafters use what their recipes cost instead
+    #[serde(default)]
+    pub energy: f32,
+    /// Slots of the inventory, the outputs of crafters
+    #[serde(default = "default_inventory")]
+    pub inventory: usize,
+    /// The sides taking items in, like the `inputs` prop of [`Tower`]
+    #[serde(default)]
+    pub inputs: String,
+    #[serde(default)]
+    pub outputs: String,
+    pub behaviour: Behaviour,
+}
+fn default_size() -> i32 {1}
+fn default_inventory() -> usize {1}
+
+fn validate_sides(list: &str) -> Result<()> {
+    for side in list.split(',').map(str::trim).filter(|side| !side.is_empty() && *side != "all") {
+        Side::from_str(side).map_err(|_| eyre!("Unknown side {:?}, expected front, right, back, left or all", side))?;
+    }
+    Ok(())
+}
+/// Parses and checks a single tower file
+pub fn parse_tower_file(raw: &str) -> Result<TowerDef> {
+    let def: TowerDef = toml::from_str(raw)?;
+    let name = def.name.clone();
+    (|| {
+        if Tower::from_str(&def.name).is_ok() {
+            bail!("A tower written in Rust already has this name")
+        }
+        if !(1..=MAX_TOWER_SIZE).contains(&def.size) {
+            bail!("The size should be between 1 and {}, got {}", MAX_TOWER_SIZE, def.size)
+        }
+        if def.energy < 0. || !def.energy.is_finite() {
+            bail!("The energy use can't be negative, got {}", def.energy)
+        }
+        if def.inventory == 0 {
+            bail!("The inventory needs at least one slot")
+        }
+        if let Some(hex) = &def.map_color {
+            u32::from_str_radix(hex, 16).map_err(|_| eyre!("Invalid map color {:?}", hex))?;
+        }
+        validate_sides(&def.inputs).context("Invalid inputs")?;
+        validate_sides(&def.outputs).context("Invalid outputs")?;
+        if let Behaviour::Collector { rate, .. } = def.behaviour {
+            if rate <= 0. || !rate.is_finite() {
+                bail!("The collection rate should be positive, got {}", rate)
+            }
+        }
+        Ok(())
+    })().with_context(|| format!("In tower {:?}", name))?;
+    Ok(def)
+}
+/// Checks what can only be seen once every file is loaded
+pub fn validate(defs: &[TowerDef]) -> Result<()> {
+    let mut names = HashSet::new();
+    for def in defs {
+        if !names.insert(def.name.as_str()) {
+            bail!("Two towers are named {:?}", def.name)
+        }
+    }
+    if defs.len() > u16::MAX as usize {
+        bail!("Too many towers")
+    }
+    Ok(())
+}
+/// Loads every `.toml` file in `dir`, fails on the first invalid one
+pub fn parse_towers_in(dir: impl AsRef<Path>) -> Result<Vec<TowerDef>> {
+    let dir = dir.as_ref();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {warn!("Can't open towers folder ! {:?}", err);return Ok(Vec::new())},
+    };
+    // Ids follow the file order, it must be the same everywhere
+    let mut paths: Vec<_> = entries.filter_map(|entry| Some(entry.ok()?.path())).filter(|path| path.extension().is_some_and(|ext| ext == "toml")).collect();
+    paths.sort();
+    let mut defs = Vec::new();
+    for path in paths {
+        let raw = std::fs::read_to_string(&path).with_context(|| format!("Can't read {}", path.display()))?;
+        defs.push(parse_tower_file(&raw).with_context(|| format!("Invalid tower file {}", path.display()))?);
+    }
+    validate(&defs)?;
+    Ok(defs)
+}
+/// Registers the declared towers, called at startup before anything looks towers up
+pub fn load_towers() -> Result<()> {
+    let defs = parse_towers_in(TOWERS_DIR)?;
+    // Already loaded if something needed them before
+    let _ = DEFINITIONS.set(defs);
+    Ok(())
+}
+/// Every declared tower, loads them if [`load_towers`] wasn't called
+pub fn definitions() -> &'static [TowerDef] {
+    DEFINITIONS.get_or_init(|| parse_towers_in(TOWERS_DIR).unwrap_or_else(|err| {
+        warn!("Can't load towers: {:?}", err);
+        Vec::new()
+    }))
+}
+pub fn find(name: &str) -> Option<Tower> {
+    definitions().iter().position(|def| def.name == name).map(|i| Tower::Defined(TowerId(i as u16)))
+}
+/// Every tower, the ones written in Rust first
+pub fn all() -> impl Iterator<Item = Tower> {
+    Tower::iter().chain((0..definitions().len()).map(|i| Tower::Defined(TowerId(i as u16))))
+}
+/// The towers the player can place, in hotbar order
+pub fn buildable() -> impl Iterator<Item = Tower> {
+    all().filter(|tower| tower.is_buildable())
+}
+pub fn texture(id: TowerId) -> Option<Texture2D> {
+    TEXTURES.read().ok()?.get(id.0 as usize)?.clone()
+}
+pub fn set_texture(id: TowerId, texture: Texture2D) {
+    let mut textures = TEXTURES.write().unwrap();
+    if textures.len() <= id.0 as usize {
+        textures.resize(id.0 as usize+1, None);
+    }
+    textures[id.0 as usize] = Some(texture);
+}
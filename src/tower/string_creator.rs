@@ -1,35 +1,106 @@
-// use super::*;
-
-// pub struct StringCreator {
-//     buffer: f32,
-//     collect_speed: f32,
-//     name: String,
-// }
-// impl StringCreator {
-//     pub fn new() -> Self {
-//         Self {
-//             buffer: 0.,
-//             collect_speed: 1.,
-//             name: "String creator".to_string(),
-//         }
-//     }
-// }
-// impl Machine for StringCreator {
-//     fn draw_gui(&mut self) -> Result<Rect> {
-//         todo!()
-//     }
-
-
-//     fn update(&mut self, map: &mut Map, dt: f32) -> Result<()> {
-//         self.buffer += self.collect_speed * dt;
-//         Ok(())
-//     }
-
-//     fn ty(&self) -> Tower {
-//         Tower::StringCreator
-//     }
-    
-//     fn serialize(&self) -> String {
-//         todo!()
-//     }
-// }
\ No newline at end of file
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Strings per second when the energy keeps up
+pub const STRING_CREATOR_RATE: f32 = 2.;
+pub const ENERGY_PER_STRING: f32 = 3.;
+/// Energy kept aside to ride out short power shortages
+pub const STRING_CREATOR_BUFFER: f32 = 30.;
+/// Energy per second drawn on top of what the strings need, until the buffer is full
+pub const STRING_CREATOR_CHARGE_RATE: f32 = 6.;
+
+/// Turns energy into strings, first through its energy buffer
+#[derive(Serialize, Deserialize)]
+pub struct StringCreator {
+    /// Progress towards the next string, from 0 to 1
+    progress: f32,
+    /// Energy received from the grid and not spent yet
+    buffer: f32,
+    inventory: Inventory,
+    /// Set by the grid every tick
+    #[serde(skip)]
+    power: f32,
+    #[serde(skip, default = "default_name")]
+    name: String,
+}
+fn default_name() -> String {"String creator".to_string()}
+impl StringCreator {
+    pub fn new() -> Self {
+        Self {
+            progress: 0.,
+            buffer: 0.,
+            inventory: Inventory::with_filters(&[Item::String]),
+            power: 0.,
+            name: default_name(),
+        }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn buffer(&self) -> f32 {self.buffer}
+    fn is_full(&self) -> bool {
+        self.inventory.space_for(Item::String) == 0
+    }
+    /// Energy per second wanted from the grid
+    fn demand(&self) -> f32 {
+        let working = if self.is_full() {0.} else {STRING_CREATOR_RATE*ENERGY_PER_STRING};
+        let charging = if self.buffer < STRING_CREATOR_BUFFER {STRING_CREATOR_CHARGE_RATE} else {0.};
+        working+charging
+    }
+}
+impl Machine for StringCreator {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, &self.name);
+        let (x, y) = (rect.x, rect.y);
+        draw_collect_button(ctx, rect, &mut self.inventory, Item::String, "strings");
+        // Progress towards the next string, then how full the energy buffer is
+        for (i, (label, fill, color)) in [
+            ("Next string", self.progress, Color::from_hex(0x9b59b6)),
+            ("Energy buffer", self.buffer/STRING_CREATOR_BUFFER, YELLOW),
+        ].into_iter().enumerate() {
+            let bar_y = y+110.+40.*i as f32;
+            draw_text(label, x+10., bar_y+18., 24., WHITE);
+            draw_rectangle(x+170., bar_y, 200., 24., Color::from_rgba(255,255,255,30));
+            draw_rectangle(x+170., bar_y, 200.*fill.clamp(0., 1.), 24., color);
+        }
+        draw_text(&format!("{:.0}/{:.0} energy - grid at {:.0}%", self.buffer, STRING_CREATOR_BUFFER, self.power*100.), x+10., y+210., 24., WHITE);
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.fast_forward(ctx, dt)
+    }
+
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        // The grid fills the buffer, strings are only made out of the buffer.
+        // Over long durations most of the energy goes straight into strings, only what is left has to fit in the buffer
+        let available = self.buffer + self.demand()*self.power*dt;
+        let space = (self.inventory.space_for(Item::String) as f32-self.progress).max(0.);
+        let made = (STRING_CREATOR_RATE*dt).min(available/ENERGY_PER_STRING).min(space);
+        self.buffer = (available - made*ENERGY_PER_STRING).min(STRING_CREATOR_BUFFER);
+        let produced = self.progress + made;
+        let leftover = self.inventory.insert(Item::String, produced as u32);
+        // Strings that didn't fit wait as progress
+        self.progress = produced.fract() + leftover as f32;
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::StringCreator
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn energy(&self) -> Option<EnergyPort> {
+        Some(EnergyPort::consumer(self.demand()))
+    }
+    fn set_power(&mut self, satisfaction: f32) {
+        self.power = satisfaction;
+    }
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
@@ -0,0 +1,173 @@
+use direction::{Direction, Side};
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Conveyors are slow but hold a few items on each tile
+pub const CONVEYOR_SPEED: f32 = 2.;
+pub const CONVEYOR_ITEMS_PER_TILE: usize = 2;
+/// Pipes move items fast, one at a time
+pub const ITEM_PIPE_SPEED: f32 = 8.;
+pub const ITEM_PIPE_ITEMS_PER_TILE: usize = 1;
+/// Stops fast-forwarding from following chains that loop
+const MAX_CHAIN_LENGTH: usize = 1024;
+
+/// Tiles per second and how many items fit on a tile, for the towers that move items
+pub fn transport_stats(tower: Tower) -> Option<(f32, usize)> {
+    match tower {
+        Tower::Conveyor => Some((CONVEYOR_SPEED, CONVEYOR_ITEMS_PER_TILE)),
+        Tower::ItemPipe => Some((ITEM_PIPE_SPEED, ITEM_PIPE_ITEMS_PER_TILE)),
+        _ => None,
+    }
+}
+/// How many items per second a transport tower can move
+pub fn throughput(tower: Tower) -> Option<f32> {
+    transport_stats(tower).map(|(speed, items)| speed*items as f32)
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct InTransit {
+    pub item: Item,
+    /// How far the item went on the tile, from 0 (entered at the back) to 1 (ready to leave at the front)
+    pub progress: f32,
+}
+
+/// Takes items from the machine behind it and carries them to the machine in front of it
+#[derive(Serialize, Deserialize)]
+pub struct Transport {
+    #[serde(skip, default = "default_ty")]
+    ty: Tower,
+    /// The front-most item first
+    items: Vec<InTransit>,
+    /// Items moved by fast-forwarding that didn't add up to a whole one yet
+    #[serde(skip)]
+    fast_forwarded: f32,
+}
+fn default_ty() -> Tower {Tower::Conveyor}
+impl Transport {
+    pub fn new(ty: Tower) -> Self {
+        Self { ty, items: Vec::new(), fast_forwarded: 0. }
+    }
+    pub fn deserialize(ty: Tower, state: toml::Table) -> Result<Self> {
+        let mut slf: Self = save::from_state(state)?;
+        slf.ty = ty;
+        Ok(slf)
+    }
+    pub fn items(&self) -> &[InTransit] {&self.items}
+    fn stats(&self) -> (f32, usize) {
+        transport_stats(self.ty).unwrap()
+    }
+    /// Whether a new item can enter at the back, `leaving` doesn't count the front item
+    fn entry_free(&self, leaving: bool) -> bool {
+        let (_, per_tile) = self.stats();
+        let items = if leaving {&self.items[1..]} else {&self.items[..]};
+        items.len() < per_tile && items.last().is_none_or(|last| last.progress >= 1./per_tile as f32)
+    }
+    /// The item ready to leave at the front
+    fn ready(&self) -> Option<Item> {
+        self.items.first().filter(|front| front.progress >= 1.).map(|front| front.item)
+    }
+    fn is_transport(machine: &dyn Machine) -> bool {
+        transport_stats(machine.ty()).is_some()
+    }
+}
+impl Machine for Transport {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, &format!("{:?}", self.ty));
+        draw_text(&format!("Moves {:.0} items/s", throughput(self.ty).unwrap()), rect.x+10., rect.y+80., 32., WHITE);
+        for (i, transit) in self.items.iter().enumerate() {
+            draw_text(&format!("{:?} ({:.0}%)", transit.item, transit.progress*100.), rect.x+10., rect.y+120.+28.*i as f32, 24., WHITE);
+        }
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        let (speed, per_tile) = self.stats();
+        let spacing = 1./per_tile as f32;
+        // Items can't go past the front of the tile, nor get closer than `spacing` to the item ahead
+        let mut limit = 1.;
+        for transit in &mut self.items {
+            transit.progress = (transit.progress+speed*dt).min(limit);
+            limit = transit.progress-spacing;
+        }
+        let leaving = self.ready();
+        let front = ctx.target(Side::Front).map(|(coords, _)| coords);
+        if let (Some(item), Some(front)) = (leaving, front) {
+            ctx.transfer(ctx.coords, front, item, 1);
+        }
+        // Other transports push their items themselves.
+        // The front item is pushed first, if it can't leave this transfer won't find any space
+        if self.entry_free(leaving.is_some()) {
+            let output = ctx.source(Side::Back).filter(|(_, machine)| !Self::is_transport(*machine))
+                .and_then(|(coords, machine)| Some((coords, *machine.outputs().first()?)));
+            if let Some((back, stack)) = output {
+                ctx.transfer(back, ctx.coords, stack.item, 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn fast_forward(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        // In a chain, only the first transport works: it moves items at the chain's slowest throughput,
+        // straight from the source behind the chain to the machine after it
+        let Some((back, source)) = ctx.source(Side::Back).filter(|(_, machine)| !Self::is_transport(*machine)) else {return Ok(())};
+        let Some(stack) = source.outputs().first().copied() else {return Ok(())};
+        let mut rate = throughput(self.ty).unwrap();
+        let (mut end, mut direction) = (ctx.side(Side::Front), ctx.facing);
+        let mut target = None;
+        for _ in 0..MAX_CHAIN_LENGTH {
+            let (Some(next), Some(facing)) = (ctx.get(end), ctx.facing_of(end)) else {break};
+            if !next.ty().accepts_from(facing, direction.opposite()) {break}
+            let Some(next_rate) = throughput(next.ty()) else {
+                target = Some(end);
+                break
+            };
+            rate = rate.min(next_rate);
+            (end, direction) = (end+facing.offset(), facing);
+        }
+        let Some(target) = target else {return Ok(())};
+        self.fast_forwarded += rate*dt;
+        let amount = self.fast_forwarded as u32;
+        if amount > 0 {
+            self.fast_forwarded -= amount as f32;
+            ctx.transfer(back, target, stack.item, amount.min(stack.amount));
+        }
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        self.ty
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn draw_overlay(&self, pos: Vec2, tilesize: f32, facing: Direction) {
+        let center = pos+Vec2::splat(tilesize/2.);
+        let dir = vec2i_to_f(facing.offset());
+        // Arrow pointing to the front
+        let tip = center+dir*tilesize*0.4;
+        let side = dir.perp()*tilesize*0.15;
+        draw_triangle(tip, tip-dir*tilesize*0.2+side, tip-dir*tilesize*0.2-side, Color::from_rgba(255,255,255,120));
+        let size = tilesize*0.3;
+        for transit in &self.items {
+            let at = center+dir*tilesize*(transit.progress-0.5);
+            draw_rectangle(at.x-size/2., at.y-size/2., size, size, transit.item.color());
+        }
+    }
+
+    fn space_for(&self, item: Item) -> u32 {
+        self.entry_free(false) as u32
+    }
+    fn insert(&mut self, item: Item, amount: u32) -> u32 {
+        if amount == 0 || !self.entry_free(false) {return amount}
+        self.items.push(InTransit { item, progress: 0. });
+        amount-1
+    }
+    fn take(&mut self, item: Item, amount: u32) -> u32 {
+        if amount == 0 || self.ready() != Some(item) {return 0}
+        self.items.remove(0);
+        1
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+pub const UNSORTED_DEPOSIT_SLOTS: usize = 20;
+/// Slots added by each capacity upgrade
+pub const UNSORTED_DEPOSIT_SLOTS_PER_LEVEL: usize = 10;
+/// Items per second that can be taken out, you gotta find what you need in this mess
+pub const UNSORTED_DEPOSIT_OUTPUT_RATE: f32 = 1.;
+
+/// Fast to put stuff into, slow to get stuff out
+#[derive(Serialize, Deserialize)]
+pub struct UnsortedDeposit {
+    inventory: Inventory,
+    /// Seconds until the next item can be taken out
+    cooldown: f32,
+    /// Capacity upgrades bought, see [`MAX_STORAGE_LEVEL`]
+    #[serde(default)]
+    level: u32,
+}
+impl UnsortedDeposit {
+    pub fn new() -> Self {
+        Self {
+            inventory: Inventory::new(UNSORTED_DEPOSIT_SLOTS),
+            cooldown: 0.,
+            level: 0,
+        }
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+    pub fn level(&self) -> u32 {self.level}
+    /// Buys the next capacity upgrade with items from `payer`, returns whether it could
+    pub fn try_upgrade(&mut self, payer: &mut Inventory) -> bool {
+        let cost = storage_upgrade_cost(self.level);
+        if self.level >= MAX_STORAGE_LEVEL || !payer.take_exact(cost.item, cost.amount) {return false}
+        self.level += 1;
+        self.inventory.grow(UNSORTED_DEPOSIT_SLOTS_PER_LEVEL);
+        true
+    }
+}
+impl Machine for UnsortedDeposit {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Unsorted item deposit");
+        draw_collect_all_button(ctx, rect, &mut self.inventory);
+        if draw_upgrade_button(rect.x+230., rect.y+80., self.level) {
+            self.try_upgrade(&mut ctx.player.inventory);
+        }
+        let used = self.inventory.slots().iter().filter(|slot| slot.stack.is_some()).count();
+        draw_text(&format!("{}/{} slots used - items come out one per second", used, self.inventory.capacity()), rect.x+10., rect.y+120., 24., WHITE);
+        for (i, stack) in self.inventory.contents().iter().enumerate() {
+            draw_text(&format!("{:?}: {}", stack.item, stack.amount), rect.x+10., rect.y+150.+28.*i as f32, 24., WHITE);
+        }
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        self.cooldown = (self.cooldown-dt).max(0.);
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::UnsortedDeposit
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn is_idle(&self) -> bool {self.cooldown <= 0.}
+
+    fn outputs(&self) -> Vec<ItemStack> {
+        if self.cooldown > 0. {return Vec::new()}
+        self.inventory.contents()
+    }
+    fn take(&mut self, item: Item, amount: u32) -> u32 {
+        if self.cooldown > 0. || amount == 0 {return 0}
+        let taken = self.inventory.take(item, 1);
+        // Asking for something it doesn't have doesn't hold back the next item
+        if taken > 0 {
+            self.cooldown = 1./UNSORTED_DEPOSIT_OUTPUT_RATE;
+        }
+        taken
+    }
+
+    fn inventory(&self) -> Option<&Inventory> {Some(&self.inventory)}
+    fn inventory_mut(&mut self) -> Option<&mut Inventory> {Some(&mut self.inventory)}
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Energy per second collected from the vacuum
+pub const VACUUM_ENERGY_RATE: f32 = 5.;
+
+/// Collects energy from space's vacuum, not very fast but you need to start somewhere
+#[derive(Serialize, Deserialize)]
+pub struct VacuumCollector {}
+impl VacuumCollector {
+    pub fn new() -> Self {
+        Self {}
+    }
+    pub fn deserialize(state: toml::Table) -> Result<Self> {
+        save::from_state(state)
+    }
+}
+impl Machine for VacuumCollector {
+    fn draw_gui(&mut self, ctx: &mut GuiCtx) -> Result<Rect> {
+        let rect = draw_panel(ctx, "Vacuum energy collector");
+        draw_text(&format!("Collecting {:.1} energy/s", VACUUM_ENERGY_RATE), rect.x+10., rect.y+80., 32., WHITE);
+        if ctx.grid.is_none() {
+            draw_text("Not connected to anything", rect.x+10., rect.y+120., 24., ORANGE);
+        }
+        Ok(rect)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn ty(&self) -> Tower {
+        Tower::VacuumCollector
+    }
+
+    fn serialize(&self) -> Result<toml::Table> {
+        save::to_state(self)
+    }
+
+    fn is_idle(&self) -> bool {true}
+
+    fn energy(&self) -> Option<EnergyPort> {
+        Some(EnergyPort::generator(VACUUM_ENERGY_RATE))
+    }
+}
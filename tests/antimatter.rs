@@ -0,0 +1,69 @@
+use initerse::{clock::TICKS_PER_SECOND, direction::Direction, item::Item, tiles::World, tower::{antimatter_collector::{self, DROPOFF_AMOUNT, DROPOFF_INTERVAL}, Tower}, *};
+
+mod common;
+use common::*;
+
+fn until_dropoff(world: &World, coords: IVec2) -> f32 {
+    antimatter_collector::deserialize(world.get_tower(&coords).serialize().unwrap()).unwrap().until_dropoff()
+}
+fn dropoff() -> World {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), Tower::AntimatterCollector.new_machine().unwrap());
+    world
+}
+
+#[test]
+fn scientists_drop_off_crates() {
+    let mut world = dropoff();
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*19).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::Antimatter), 0);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND+1).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::Antimatter), DROPOFF_AMOUNT);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*20).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::Antimatter), DROPOFF_AMOUNT*2);
+}
+
+#[test]
+fn crates_wait_for_room() {
+    let mut world = dropoff();
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*70).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::Antimatter), Item::Antimatter.stack_size());
+    assert_eq!(until_dropoff(&world, ivec2(0, 0)), 0.);
+    // The waiting crate comes in as soon as there is room
+    world.try_get_tower_mut(&ivec2(0, 0)).unwrap().take(Item::Antimatter, DROPOFF_AMOUNT);
+    world.tick(Vec2::ZERO, 1).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::Antimatter), Item::Antimatter.stack_size());
+    assert!((until_dropoff(&world, ivec2(0, 0))-DROPOFF_INTERVAL).abs() < 0.1);
+}
+
+#[test]
+fn dropoff_state_is_saved() {
+    let mut world = dropoff();
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*33).unwrap();
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(count(&loaded, ivec2(0, 0), Item::Antimatter), DROPOFF_AMOUNT);
+    assert_eq!(until_dropoff(&loaded, ivec2(0, 0)), until_dropoff(&world, ivec2(0, 0)));
+    assert!((until_dropoff(&loaded, ivec2(0, 0))-7.).abs() < 0.1);
+    // Saves from before the state was kept
+    assert_eq!(antimatter_collector::deserialize(toml::Table::new()).unwrap().until_dropoff(), DROPOFF_INTERVAL);
+}
+
+#[test]
+fn antimatter_leaves_through_the_front() {
+    let mut world = dropoff();
+    world.set_facing(&ivec2(0, 0), Direction::Down);
+    // Behind and on the side of the dropoff, nothing comes out
+    world.set_tower(ivec2(1, 0), Tower::Conveyor.new_machine().unwrap());
+    world.set_tower(ivec2(2, 0), Tower::UnsortedDeposit.new_machine().unwrap());
+    world.set_tower(ivec2(0, -1), Tower::Conveyor.new_machine().unwrap());
+    world.set_facing(&ivec2(0, -1), Direction::Up);
+    world.set_tower(ivec2(0, -2), Tower::UnsortedDeposit.new_machine().unwrap());
+    // In front of it
+    world.set_tower(ivec2(0, 1), Tower::Conveyor.new_machine().unwrap());
+    world.set_facing(&ivec2(0, 1), Direction::Down);
+    world.set_tower(ivec2(0, 2), Tower::UnsortedDeposit.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*25).unwrap();
+    assert_eq!(count(&world, ivec2(0, 2), Item::Antimatter), DROPOFF_AMOUNT);
+    assert_eq!(count(&world, ivec2(2, 0), Item::Antimatter), 0);
+    assert_eq!(count(&world, ivec2(0, -2), Item::Antimatter), 0);
+}
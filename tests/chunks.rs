@@ -0,0 +1,66 @@
+use initerse::{chunk::{chunk_of, CHUNK_SIZE}, clock::TICKS_PER_SECOND, simulation::SimulationMode, tiles::World, tower::Tower, *};
+
+mod common;
+use common::*;
+
+#[test]
+fn negative_coords_use_the_chunk_below() {
+    assert_eq!(chunk_of(ivec2(0, 0)), ivec2(0, 0));
+    assert_eq!(chunk_of(ivec2(CHUNK_SIZE-1, CHUNK_SIZE)), ivec2(0, 1));
+    assert_eq!(chunk_of(ivec2(-1, -CHUNK_SIZE)), ivec2(-1, -1));
+    assert_eq!(chunk_of(ivec2(-CHUNK_SIZE-1, 0)), ivec2(-2, 0));
+}
+
+#[test]
+fn far_chunks_only_update_when_active() {
+    let far = ivec2(CHUNK_SIZE*20, 0);
+    let mut world = World::new(1022);
+    world.simulation_mut().mode = SimulationMode::Nearby;
+    world.set_tower(far, Tower::Electron.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2+1).unwrap();
+    assert_eq!(strings(&world, far), 0);
+
+    world.set_chunk_active(chunk_of(far), true);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2+1).unwrap();
+    assert_eq!(strings(&world, far), 2);
+}
+
+#[test]
+fn only_dirty_chunks_are_saved_again() {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), Tower::Electron.new_machine().unwrap());
+    world.set_tower(ivec2(CHUNK_SIZE, 0), Tower::Electron.new_machine().unwrap());
+    world.serialize().unwrap();
+    assert!(world.map().chunks().all(|(_, chunk)| !chunk.is_dirty()));
+
+    world.set_tower(ivec2(1, 0), Tower::Empty.new_machine().unwrap());
+    world.try_get_tower_mut(&ivec2(0, 0)).unwrap();
+    let dirty: Vec<_> = world.map().chunks().filter(|(_, chunk)| chunk.is_dirty()).map(|(coords, _)| coords).collect();
+    assert_eq!(dirty, [ivec2(0, 0)]);
+}
+
+#[test]
+fn removed_machines_are_not_saved() {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(3, 3), Tower::Electron.new_machine().unwrap());
+    world.set_tower(ivec2(4, 3), Tower::Electron.new_machine().unwrap());
+    world.serialize().unwrap();
+    world.set_tower(ivec2(3, 3), Tower::Empty.new_machine().unwrap());
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.machine_count(), 1);
+    assert_eq!(loaded.get_tower(&ivec2(4, 3)).ty(), Tower::Electron);
+}
+
+#[test]
+fn idle_machines_dont_make_their_chunk_dirty() {
+    let mut world = World::new(1022);
+    // A power grid without consumers, and storage nobody uses
+    world.set_tower(ivec2(0, 0), Tower::PowerNode.new_machine().unwrap());
+    world.set_tower(ivec2(1, 0), Tower::VacuumCollector.new_machine().unwrap());
+    world.set_tower(ivec2(2, 0), Tower::ItemDeposit.new_machine().unwrap());
+    world.set_tower(ivec2(CHUNK_SIZE, 0), Tower::Electron.new_machine().unwrap());
+    world.serialize().unwrap();
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    let dirty: Vec<_> = world.map().chunks().filter(|(_, chunk)| chunk.is_dirty()).map(|(coords, _)| coords).collect();
+    assert_eq!(dirty, [ivec2(1, 0)]);
+}
@@ -0,0 +1,19 @@
+//! Fixtures shared by the integration tests, every test file only uses some of them
+#![allow(dead_code)]
+use initerse::{item::Item, tiles::World, tower::Tower, *};
+
+/// A world with only these machines
+pub fn world_with(towers: &[(IVec2, Tower)]) -> World {
+    let mut world = World::new(1022);
+    for (coords, tower) in towers {
+        world.set_tower(*coords, tower.new_machine().unwrap());
+    }
+    world
+}
+/// How many of an item the machine at `coords` holds, 0 for machines without an inventory
+pub fn count(world: &World, coords: IVec2, item: Item) -> u32 {
+    world.get_tower(&coords).inventory().map_or(0, |inv| inv.count(item))
+}
+pub fn strings(world: &World, coords: IVec2) -> u32 {
+    count(world, coords, Item::String)
+}
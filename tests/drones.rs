@@ -0,0 +1,120 @@
+use initerse::{clock::TICKS_PER_SECOND, drone::{Drone, DroneModule, DRONE_CAPACITY}, entity::{Body, EntityType}, item::{Inventory, Item}, tiles::World, tower::{drone_hub::{DroneHub, DROPOFF_SLOT, PICKUP_SLOT}, Tower}, *};
+
+/// A drone hub at the origin carrying from a storage crate at (6, 0) to an item deposit at (0, 6).
+/// `powered` puts a vacuum collector (5 energy/s) next to the hub
+fn drone_route(powered: bool) -> World {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), Tower::DroneHub.new_machine().unwrap());
+    if powered {
+        world.set_tower(ivec2(-1, 0), Tower::VacuumCollector.new_machine().unwrap());
+    }
+    world.set_tower(ivec2(6, 0), Tower::named("Storage crate").unwrap().new_machine().unwrap());
+    world.set_tower(ivec2(0, 6), Tower::ItemDeposit.new_machine().unwrap());
+    world.try_get_tower_mut(&ivec2(6, 0)).unwrap().insert(Item::String, 40);
+    let hub = world.try_get_tower_mut(&ivec2(0, 0)).unwrap();
+    hub.pick_cell(PICKUP_SLOT, ivec2(6, 0));
+    hub.pick_cell(DROPOFF_SLOT, ivec2(0, 6));
+    world
+}
+fn drones(world: &World) -> usize {
+    world.entities().of_type(EntityType::Drone).count()
+}
+/// The only drone of the world
+fn drone(world: &World) -> (Body, Drone) {
+    let entity = world.entities().of_type(EntityType::Drone).next().unwrap();
+    (entity.body, Drone::deserialize(entity.behaviour().serialize().unwrap()).unwrap())
+}
+fn delivered(world: &World, item: Item) -> u32 {
+    world.get_tower(&ivec2(0, 6)).inventory().unwrap().count(item)
+}
+/// Swaps the hub at the origin for one with these modules and filter, keeping its targets
+fn with_modules(world: &mut World, modules: &[DroneModule], filter: Option<Item>) {
+    let mut hub = DroneHub::deserialize(world.get_tower(&ivec2(0, 0)).serialize().unwrap()).unwrap();
+    let mut strings = Inventory::new(10);
+    strings.insert(Item::String, 1000);
+    for module in modules {
+        assert!(hub.install(*module, &mut strings));
+    }
+    hub.set_filter(filter);
+    world.set_tower(ivec2(0, 0), Box::new(hub) as Box<dyn tower::Machine>);
+}
+
+#[test]
+fn hubs_launch_a_single_drone() {
+    let mut world = drone_route(true);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    assert_eq!(drones(&world), 1);
+    assert_eq!(drone(&world).1.home(), ivec2(0, 0));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    assert_eq!(drones(&world), 1);
+}
+
+#[test]
+fn drones_carry_items_between_machines() {
+    let mut world = drone_route(true);
+    // 6 tiles there and 6 back at 4 tiles/s, 5 strings a trip
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    let count = delivered(&world, Item::String);
+    assert!((10..=20).contains(&count), "{count}");
+    assert_eq!(world.get_tower(&ivec2(6, 0)).inventory().unwrap().count(Item::String)+count+drone(&world).1.cargo().map_or(0, |c| c.amount), 40);
+}
+
+#[test]
+fn drones_need_power() {
+    let mut world = drone_route(false);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    assert_eq!(drone(&world).0.pos, vec2(0.5, 0.5));
+    assert_eq!(delivered(&world, Item::String), 0);
+}
+
+#[test]
+fn drones_go_away_with_their_hub() {
+    let mut world = drone_route(true);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    world.set_tower(ivec2(0, 0), Tower::Empty.new_machine().unwrap());
+    world.tick(Vec2::ZERO, 1).unwrap();
+    assert_eq!(drones(&world), 0);
+}
+
+#[test]
+fn modules_change_what_drones_carry() {
+    let mut world = drone_route(true);
+    world.try_get_tower_mut(&ivec2(6, 0)).unwrap().insert(Item::Iron, 40);
+    with_modules(&mut world, &[DroneModule::Filter, DroneModule::Capacity], Some(Item::Iron));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*4).unwrap();
+    // A single trip, with 5 more items than usual
+    assert_eq!(delivered(&world, Item::Iron), DRONE_CAPACITY+5);
+    assert_eq!(delivered(&world, Item::String), 0);
+
+    let mut strings = Inventory::new(1);
+    let mut hub = DroneHub::new();
+    assert!(!hub.install(DroneModule::Speed, &mut strings), "modules cost strings");
+}
+
+#[test]
+fn drones_are_saved() {
+    let mut world = drone_route(true);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2).unwrap();
+    let before = drone(&world);
+    assert!(before.1.cargo().is_some());
+    let mut loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(drone(&loaded), before);
+    // The hub remembers its targets and that its drone is already out
+    loaded.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    assert_eq!(drones(&loaded), 1);
+    assert!(delivered(&loaded, Item::String) > 0);
+}
+
+#[test]
+fn drones_keep_flying_while_offline() {
+    const NOW: u64 = 1_700_000_000;
+    let mut live = drone_route(true);
+    live.tick(Vec2::ZERO, TICKS_PER_SECOND*20).unwrap();
+    let mut away = drone_route(true);
+    away.meta_mut().last_played = NOW-20;
+    away.offline_progress(NOW, 3600.).unwrap();
+    // Several trips, not a single pickup for the whole time away
+    let (live, away) = (delivered(&live, Item::String), delivered(&away, Item::String));
+    assert!(away >= 3*DRONE_CAPACITY, "{away}");
+    assert!(live.abs_diff(away) <= DRONE_CAPACITY, "{} live, {} offline", live, away);
+}
@@ -0,0 +1,72 @@
+use initerse::{clock::TICKS_PER_SECOND, item::Item, tiles::World, tower::Tower, *};
+
+mod common;
+use common::*;
+
+fn stored(world: &World, coords: IVec2) -> f32 {
+    world.get_tower(&coords).energy().map_or(0., |port| port.stored)
+}
+
+#[test]
+fn powered_consumer_runs_at_full_speed() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::VacuumCollector), (ivec2(1, 0), Tower::PhotonEmitter)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10+1).unwrap();
+    assert_eq!(count(&world, ivec2(1, 0), Item::Photon), 10);
+    let stats = world.grid_stats(ivec2(1, 0)).unwrap();
+    assert_eq!((stats.machines, stats.satisfaction), (2, 1.));
+}
+
+#[test]
+fn unpowered_consumer_does_nothing() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::PhotonEmitter)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::Photon), 0);
+    assert_eq!(world.grid_stats(ivec2(0, 0)).unwrap().satisfaction, 0.);
+}
+
+#[test]
+fn shortage_throttles_consumers() {
+    let mut world = world_with(&[
+        (ivec2(0, 0), Tower::PhotonEmitter),
+        (ivec2(1, 0), Tower::VacuumCollector),
+        (ivec2(2, 0), Tower::PhotonEmitter),
+    ]);
+    // 5 energy/s for 8 energy/s of demand
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*16+1).unwrap();
+    assert_eq!(world.grid_stats(ivec2(1, 0)).unwrap().satisfaction, 5./8.);
+    assert_eq!(count(&world, ivec2(0, 0), Item::Photon), 10);
+    assert_eq!(count(&world, ivec2(2, 0), Item::Photon), 10);
+}
+
+#[test]
+fn batteries_store_surplus_and_cover_shortage() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::VacuumCollector), (ivec2(1, 0), Tower::Battery)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    assert!((stored(&world, ivec2(1, 0))-50.).abs() < 0.1);
+
+    world.set_tower(ivec2(0, 0), Tower::Empty.new_machine().unwrap());
+    // Batteries are 2x2, the emitter is next to its right half
+    world.set_tower(ivec2(3, 0), Tower::PhotonEmitter.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10+1).unwrap();
+    assert_eq!(count(&world, ivec2(3, 0), Item::Photon), 10);
+    assert!((stored(&world, ivec2(1, 0))-10.).abs() < 0.1);
+}
+
+#[test]
+fn power_nodes_join_distant_machines() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::VacuumCollector), (ivec2(7, 0), Tower::PhotonEmitter)]);
+    world.tick(Vec2::ZERO, 1).unwrap();
+    assert_eq!(world.energy().grid_count(), 2);
+    world.set_tower(ivec2(3, 0), Tower::PowerNode.new_machine().unwrap());
+    world.tick(Vec2::ZERO, 1).unwrap();
+    assert_eq!(world.energy().grid_count(), 1);
+    assert_eq!(world.grid_stats(ivec2(7, 0)).unwrap().production, 5.);
+}
+
+#[test]
+fn battery_charge_is_saved() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::VacuumCollector), (ivec2(0, 1), Tower::Battery)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*4).unwrap();
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(stored(&loaded, ivec2(0, 1)), stored(&world, ivec2(0, 1)));
+}
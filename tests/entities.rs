@@ -0,0 +1,89 @@
+use initerse::{clock::TICKS_PER_SECOND, drone::Drone, entity::{Behaviour, Body, Entities, EntityCtx, EntityId, EntityType}, pathfinding::Pathfinder, tiles::{Map, World}, tower::Tower, *};
+
+/// Flies in a straight line for a while
+struct Comet {
+    vel: Vec2,
+    lifetime: f32,
+}
+impl Behaviour for Comet {
+    fn update(&mut self, body: &mut Body, _ctx: &mut EntityCtx, dt: f32) -> Result<bool> {
+        body.vel = self.vel;
+        self.lifetime -= dt;
+        Ok(self.lifetime > 0.)
+    }
+    fn draw(&self, _body: &Body, _pos: Vec2, _tilesize: f32) {}
+    fn ty(&self) -> EntityType {EntityType::Drone}
+    fn serialize(&self) -> Result<toml::Table> {Ok(toml::Table::new())}
+}
+fn comet(vel: Vec2, lifetime: f32) -> Box<dyn Behaviour> {
+    Box::new(Comet { vel, lifetime })
+}
+fn ids<'a>(entities: impl Iterator<Item = &'a entity::Entity>) -> Vec<u64> {
+    let mut ids: Vec<u64> = entities.map(|entity| entity.id().0).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn ids_are_never_reused() {
+    let mut entities = Entities::new();
+    let a = entities.spawn(Vec2::ZERO, comet(Vec2::ZERO, 1.));
+    let b = entities.spawn(Vec2::ZERO, comet(Vec2::ZERO, 1.));
+    assert_ne!(a, b);
+    entities.remove(b).unwrap();
+    let c = entities.spawn(Vec2::ZERO, comet(Vec2::ZERO, 1.));
+    assert_eq!(c, EntityId(2));
+    assert!(entities.get(b).is_none());
+    assert_eq!(entities.len(), 2);
+}
+
+#[test]
+fn entities_move_and_expire() {
+    let mut entities = Entities::new();
+    let mut map = Map::new();
+    let fast = entities.spawn(vec2(0.5, 0.5), comet(vec2(2., 0.), 10.));
+    let short = entities.spawn(vec2(0.5, 0.5), comet(Vec2::ZERO, 0.5));
+    for _ in 0..TICKS_PER_SECOND {
+        entities.update(&mut map, &mut Pathfinder::new(), &mut Vec::new(), clock::TICK_DT).unwrap();
+    }
+    let pos = entities.get(fast).unwrap().body.pos;
+    assert!((pos.x-2.5).abs() < 0.01, "{pos}");
+    assert!(entities.get(short).is_none());
+}
+
+#[test]
+fn spatial_queries_find_nearby_entities() {
+    let mut entities = Entities::new();
+    // Spread over several chunks, on both sides of the origin
+    for pos in [vec2(0.5, 0.5), vec2(3., 4.), vec2(-20., 1.), vec2(15.9, 0.), vec2(16.1, 0.), vec2(100., 100.)] {
+        entities.spawn(pos, comet(Vec2::ZERO, 1.));
+    }
+    assert_eq!(ids(entities.within_radius(Vec2::ZERO, 5.)), [0, 1]);
+    assert_eq!(ids(entities.within_radius(vec2(16., 0.), 0.5)), [3, 4]);
+    assert_eq!(ids(entities.within_radius(vec2(-20., 0.), 2.)), [2]);
+    assert_eq!(ids(entities.in_rect(Rect::new(-30., -1., 46., 6.))), [0, 1, 2, 3]);
+    assert_eq!(ids(entities.in_rect(Rect::new(90., 90., 20., 20.))), [5]);
+    // The index follows entities as they move
+    let mut map = Map::new();
+    let id = entities.spawn(vec2(0.5, 0.5), comet(vec2(0., 40.), 10.));
+    entities.update(&mut map, &mut Pathfinder::new(), &mut Vec::new(), 1.).unwrap();
+    assert!(entities.within_radius(vec2(0.5, 40.5), 1.).any(|entity| entity.id() == id));
+    assert!(!entities.within_radius(Vec2::ZERO, 5.).any(|entity| entity.id() == id));
+}
+
+#[test]
+fn entities_are_saved_with_their_id() {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), Tower::DroneHub.new_machine().unwrap());
+    world.entities_mut().spawn(vec2(2., 3.), comet(Vec2::ZERO, 1.));
+    world.entities_mut().remove(EntityId(0));
+    let drone = world.entities_mut().spawn(vec2(4.5, -2.), Box::new(Drone::new(ivec2(0, 0))));
+    let raw = world.serialize().unwrap();
+    assert!(raw.contains("type = \"Drone\""), "{raw}");
+    let mut loaded = World::load(raw).unwrap();
+    assert_eq!(loaded.entities().len(), 1);
+    assert_eq!(loaded.entities().get(drone).unwrap().body.pos, vec2(4.5, -2.));
+    assert!(loaded.entities().get(drone).is_some_and(|entity| entity.ty() == EntityType::Drone));
+    // New ids still come after the saved ones
+    assert_eq!(loaded.entities_mut().spawn(Vec2::ZERO, comet(Vec2::ZERO, 1.)), EntityId(drone.0+1));
+}
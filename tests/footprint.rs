@@ -0,0 +1,62 @@
+use initerse::{tiles::World, tower::Tower, *};
+
+mod common;
+use common::*;
+
+#[test]
+fn big_machines_cover_their_footprint() {
+    let world = world_with(&[(ivec2(3, 4), Tower::Battery)]);
+    assert_eq!(Tower::Battery.size(), 2);
+    for cell in [ivec2(3, 4), ivec2(4, 4), ivec2(3, 5), ivec2(4, 5)] {
+        assert_eq!(world.get_tower(&cell).ty(), Tower::Battery);
+        assert_eq!(world.anchor_of(&cell), Some(ivec2(3, 4)));
+    }
+    assert!(world.try_get_tower(&ivec2(5, 4)).is_none());
+    assert_eq!(world.machine_count(), 1);
+}
+
+#[test]
+fn removing_any_cell_removes_the_whole_machine() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::Battery)]);
+    let removed = world.set_tower(ivec2(1, 1), Tower::Empty.new_machine().unwrap());
+    assert_eq!(removed.map(|machine| machine.ty()), Some(Tower::Battery));
+    for cell in Tower::Battery.footprint(ivec2(0, 0)) {
+        assert!(world.try_get_tower(&cell).is_none());
+    }
+    assert_eq!(world.machine_count(), 0);
+}
+
+#[test]
+fn overlapping_placement_is_detected() {
+    let world = world_with(&[(ivec2(0, 0), Tower::Electron)]);
+    assert!(!world.fits(ivec2(-1, -1), Tower::Battery));
+    assert!(world.fits(ivec2(1, 0), Tower::Battery));
+    assert!(!world.fits(ivec2(0, 0), Tower::Electron));
+}
+
+#[test]
+fn placing_over_a_big_machine_replaces_it() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::Battery)]);
+    world.set_tower(ivec2(1, 1), Tower::Electron.new_machine().unwrap());
+    assert_eq!(world.get_tower(&ivec2(1, 1)).ty(), Tower::Electron);
+    assert!(world.try_get_tower(&ivec2(0, 0)).is_none());
+    assert_eq!(world.machine_count(), 1);
+}
+
+#[test]
+fn footprint_survives_saving() {
+    let mut world = world_with(&[(ivec2(-1, 15), Tower::Battery)]);
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.machine_count(), 1);
+    // The machine spans two chunks
+    assert_eq!(loaded.anchor_of(&ivec2(0, 16)), Some(ivec2(-1, 15)));
+}
+
+#[test]
+fn big_machines_join_grids_from_any_cell() {
+    // The collector only touches the bottom right cell of the battery
+    let mut world = world_with(&[(ivec2(0, 0), Tower::Battery), (ivec2(2, 1), Tower::VacuumCollector)]);
+    world.tick(Vec2::ZERO, 1).unwrap();
+    assert_eq!(world.energy().grid_count(), 1);
+    assert_eq!(world.grid_stats(ivec2(1, 1)).unwrap().machines, 2);
+}
@@ -0,0 +1,52 @@
+use initerse::{clock::TICKS_PER_SECOND, item::Item, tiles::World, tower::Tower, *};
+
+mod common;
+use common::*;
+
+#[test]
+fn electron_collects_a_string_per_second() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::Electron)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10+1).unwrap();
+    assert_eq!(count(&world, ivec2(0, 0), Item::String), 10);
+}
+
+#[test]
+fn antimatter_collector_stops_when_full() {
+    let mut world = world_with(&[(ivec2(3, 2), Tower::AntimatterCollector)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*60).unwrap();
+    assert_eq!(count(&world, ivec2(3, 2), Item::Antimatter), Item::Antimatter.stack_size());
+}
+
+#[test]
+fn save_and_load_keeps_machines() {
+    let mut world = world_with(&[
+        (ivec2(-1, -1), Tower::Electron),
+        (ivec2(1, 1), Tower::Electron),
+        (ivec2(4, 0), Tower::AntimatterCollector),
+    ]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*5+1).unwrap();
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.seed(), world.seed());
+    assert_eq!(loaded.machine_count(), 3);
+    assert_eq!(loaded.get_tower(&ivec2(4, 0)).ty(), Tower::AntimatterCollector);
+    assert_eq!(count(&loaded, ivec2(-1, -1), Item::String), 5);
+    assert_eq!(count(&loaded, ivec2(1, 1), Item::String), 5);
+}
+
+#[test]
+fn loaded_world_keeps_ticking() {
+    let mut world = world_with(&[(ivec2(0, 0), Tower::Electron)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2+1).unwrap();
+    let mut loaded = World::load(world.serialize().unwrap()).unwrap();
+    loaded.tick(Vec2::ZERO, TICKS_PER_SECOND*3).unwrap();
+    assert_eq!(count(&loaded, ivec2(0, 0), Item::String), 5);
+}
+
+#[test]
+fn loads_existing_save() {
+    let world = World::load(std::fs::read_to_string("saves/1022").unwrap()).unwrap();
+    assert_eq!(world.seed(), 1022);
+    assert_eq!(world.machine_count(), 19);
+    assert_eq!(count(&world, ivec2(-1, -1), Item::String), 40);
+}
+
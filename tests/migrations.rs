@@ -0,0 +1,103 @@
+use initerse::{clock::TICKS_PER_SECOND, direction::Direction, item::Item, save::{migrations, SaveFile, SAVE_VERSION}, tiles::World, tower::Tower, *};
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(format!("tests/fixtures/saves/{}", name)).unwrap()
+}
+
+#[test]
+fn every_version_has_a_migration_and_a_fixture() {
+    for version in 1..SAVE_VERSION {
+        assert!(migrations::MIGRATIONS.iter().any(|(v, _)| *v == version), "No migration from version {}", version);
+        let world = World::load(fixture(&format!("v{}.toml", version)));
+        assert!(world.is_ok(), "Can't load v{} fixture: {:?}", version, world.err());
+    }
+}
+
+#[test]
+fn legacy_fixture_loads() {
+    let world = World::load(fixture("v0_legacy")).unwrap();
+    assert_eq!(world.machine_count(), 19);
+}
+
+#[test]
+fn v1_saves_are_upgraded_to_the_current_version() {
+    let mut table: toml::Table = toml::from_str(&fixture("v1.toml")).unwrap();
+    assert_eq!(migrations::migrate(&mut table).unwrap(), 1);
+    assert_eq!(migrations::save_version(&table).unwrap(), SAVE_VERSION);
+    let save = SaveFile::parse(&fixture("v1.toml")).unwrap();
+    assert_eq!(save.version, SAVE_VERSION);
+    assert_eq!(save.machines.len(), 3);
+}
+
+#[test]
+fn v1_electrons_get_the_default_collect_speed() {
+    let mut world = World::load(fixture("v1.toml")).unwrap();
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2).unwrap();
+    let strings = |world: &World, coords| world.get_tower(&coords).inventory().unwrap().count(Item::String);
+    assert_eq!(strings(&world, ivec2(-1, -1)), 42);
+    assert_eq!(strings(&world, ivec2(1, 1)), 2);
+    let raw = world.serialize().unwrap();
+    assert!(raw.contains("collect_speed = 1.0"), "{}", raw);
+}
+
+#[test]
+fn saves_from_the_future_are_rejected() {
+    let mut table: toml::Table = toml::from_str(&format!("version = {}\nseed = 1\n", SAVE_VERSION+1)).unwrap();
+    assert!(migrations::migrate(&mut table).is_err());
+}
+
+#[test]
+fn current_saves_are_not_migrated() {
+    let raw = World::new(5).serialize().unwrap();
+    let mut table: toml::Table = toml::from_str(&raw).unwrap();
+    let before = table.clone();
+    assert_eq!(migrations::migrate(&mut table).unwrap(), SAVE_VERSION);
+    assert_eq!(table, before);
+}
+
+#[test]
+fn v2_worlds_are_named_after_their_seed() {
+    let save = SaveFile::parse(&fixture("v2.toml")).unwrap();
+    assert_eq!(save.meta.name, "World 1022");
+    assert_eq!(save.meta.play_time, 0.);
+    let mut world = World::load(fixture("v2.toml")).unwrap();
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    assert_eq!(world.get_tower(&ivec2(-1, -1)).inventory().unwrap().count(Item::String), 42);
+}
+
+#[test]
+fn v3_conveyors_keep_their_facing() {
+    let save = SaveFile::parse(&fixture("v3.toml")).unwrap();
+    let facings: Vec<_> = save.machines.iter().map(|m| (m.get_ref().tower, m.get_ref().facing)).collect();
+    assert_eq!(facings, [(Tower::Electron, Direction::Right), (Tower::Conveyor, Direction::Down), (Tower::UnsortedDeposit, Direction::Right)]);
+    let mut world = World::load(fixture("v3.toml")).unwrap();
+    assert_eq!(world.facing(&ivec2(0, 1)), Direction::Down);
+    // The string in transit gets to the deposit below
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND/4).unwrap();
+    assert_eq!(world.get_tower(&ivec2(0, 2)).inventory().unwrap().count(Item::String), 1);
+}
+
+#[test]
+fn v4_drones_become_entities() {
+    let save = SaveFile::parse(&fixture("v4.toml")).unwrap();
+    assert_eq!(save.entities.len(), 1);
+    let world = World::load(fixture("v4.toml")).unwrap();
+    let drone = world.entities().get(entity::EntityId(0)).unwrap();
+    assert_eq!(drone.ty(), entity::EntityType::Drone);
+    assert!((drone.body.pos-vec2(1.72, 3.28)).length() < 0.01);
+    let state = drone.behaviour().serialize().unwrap();
+    assert_eq!(state["home"], toml::Value::from(vec![0, 0]));
+    assert_eq!(state["cargo"]["amount"].as_integer(), Some(5));
+}
+
+#[test]
+fn v5_declared_item_deposits_become_built_in() {
+    let raw = fixture("v5.toml")+"\n[[machines]]\npos = [3, 3]\ntower = \"Item deposit\"\nfacing = \"Right\"\n\n[[machines.state.inventory.slots]]\n\n[machines.state.inventory.slots.stack]\namount = 7\nitem = \"String\"\n";
+    let mut world = World::load(raw).unwrap();
+    let deposit = world.get_tower(&ivec2(3, 3));
+    assert_eq!(deposit.ty(), Tower::ItemDeposit);
+    assert_eq!(deposit.inventory().unwrap().count(Item::String), 7);
+    assert_eq!(deposit.inventory().unwrap().stack_limit(Item::String), tower::item_deposit::ITEM_DEPOSIT_CAPACITY);
+    // Saved again under its new name
+    assert!(!world.serialize().unwrap().contains("Item deposit"));
+}
@@ -0,0 +1,61 @@
+use initerse::{item::Item, tiles::World, tower::Tower, *};
+
+mod common;
+use common::*;
+
+const NOW: u64 = 1_700_000_000;
+
+fn world_left(seconds_ago: u64) -> World {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), Tower::Electron.new_machine().unwrap());
+    world.set_tower(ivec2(500, 500), Tower::Electron.new_machine().unwrap());
+    world.meta_mut().last_played = NOW-seconds_ago;
+    // Goes through a save, like when the game is started again
+    World::load(world.serialize().unwrap()).unwrap()
+}
+
+#[test]
+fn machines_catch_up_on_the_time_away() {
+    let mut world = world_left(100);
+    let report = world.offline_progress(NOW, 3600.).unwrap();
+    assert_eq!(report.away, 100.);
+    assert_eq!(report.simulated, 100.);
+    assert_eq!(strings(&world, ivec2(0, 0)), 100);
+    assert_eq!(strings(&world, ivec2(500, 500)), 100);
+    assert_eq!(report.produced.len(), 1);
+    assert_eq!((report.produced[0].item, report.produced[0].amount), (Item::String, 200));
+}
+
+#[test]
+fn offline_time_is_capped() {
+    let mut world = world_left(3600);
+    let report = world.offline_progress(NOW, 150.).unwrap();
+    assert_eq!(report.away, 3600.);
+    assert_eq!(report.simulated, 150.);
+    assert_eq!(strings(&world, ivec2(0, 0)), 150);
+}
+
+#[test]
+fn disabled_offline_progress_does_nothing() {
+    let mut world = world_left(3600);
+    let report = world.offline_progress(NOW, 0.).unwrap();
+    assert!(report.is_empty());
+    assert_eq!(strings(&world, ivec2(0, 0)), 0);
+}
+
+#[test]
+fn time_away_is_only_counted_once() {
+    let mut world = world_left(100);
+    world.offline_progress(NOW, 3600.).unwrap();
+    let report = world.offline_progress(NOW, 3600.).unwrap();
+    assert!(report.is_empty());
+    assert_eq!(strings(&world, ivec2(0, 0)), 100);
+}
+
+#[test]
+fn saves_without_a_timestamp_get_no_progress() {
+    let mut world = world_left(NOW);
+    let report = world.offline_progress(NOW, 3600.).unwrap();
+    assert_eq!(report.away, 0.);
+    assert!(report.produced.is_empty());
+}
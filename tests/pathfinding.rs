@@ -0,0 +1,143 @@
+use initerse::{tiles::World, tower::Tower, *};
+
+/// Builds a map from rows of text: `#` is a power node, `T` the target, `B` the anchor of a battery (2x2), `.` is empty.
+/// The top left character is at (0, 0)
+fn map(rows: &[&str]) -> World {
+    let mut world = World::new(1022);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let tower = match c {
+                '#' | 'T' => Tower::PowerNode,
+                'B' => Tower::Battery,
+                _ => continue,
+            };
+            world.set_tower(ivec2(x as i32, y as i32), tower.new_machine().unwrap());
+        }
+    }
+    world
+}
+/// Every step is to a neighbouring cell that isn't taken by another machine
+fn assert_walkable(world: &World, from: IVec2, path: &[IVec2], target: IVec2) {
+    let mut previous = from;
+    for cell in path {
+        assert_eq!((*cell-previous).abs().element_sum(), 1, "{path:?}");
+        previous = *cell;
+    }
+    for cell in &path[..path.len()-1] {
+        assert!(world.anchor_of(cell).is_none(), "{cell} is taken: {path:?}");
+    }
+    assert_eq!(world.anchor_of(path.last().unwrap()), world.anchor_of(&target));
+}
+
+#[test]
+fn paths_are_as_short_as_possible() {
+    let mut world = map(&[
+        "..........",
+        "..........",
+        ".........T",
+    ]);
+    let path = world.find_path(ivec2(0, 0), ivec2(9, 2)).unwrap();
+    assert_eq!(path.len(), 11);
+    assert_walkable(&world, ivec2(0, 0), &path, ivec2(9, 2));
+    // Already there
+    assert_eq!(world.find_path(ivec2(9, 2), ivec2(9, 2)).unwrap(), []);
+}
+
+#[test]
+fn paths_go_around_machines() {
+    let mut world = map(&[
+        ".....#....",
+        ".....#....",
+        ".....#..T.",
+        ".....#....",
+        "..........",
+    ]);
+    let path = world.find_path(ivec2(2, 2), ivec2(8, 2)).unwrap();
+    assert_walkable(&world, ivec2(2, 2), &path, ivec2(8, 2));
+    // Down and around the bottom of the wall
+    assert_eq!(path.len(), 10);
+    assert!(path.contains(&ivec2(5, 4)));
+}
+
+#[test]
+fn walled_in_machines_cant_be_reached() {
+    let mut world = map(&[
+        "#####",
+        "#.T.#",
+        "#####",
+    ]);
+    assert!(world.find_path(ivec2(-3, 1), ivec2(2, 1)).is_none());
+    // From inside the walls it is fine
+    assert_eq!(world.find_path(ivec2(1, 1), ivec2(2, 1)).unwrap(), [ivec2(2, 1)]);
+    // Nothing there to go to
+    assert!(world.find_path(ivec2(0, 5), ivec2(3, 5)).is_none());
+}
+
+#[test]
+fn paths_stop_at_the_first_cell_of_bigger_machines() {
+    let mut world = map(&[
+        "......",
+        "....B.",
+        "......",
+    ]);
+    // Any cell of the battery is the battery
+    let path = world.find_path(ivec2(0, 2), ivec2(4, 1)).unwrap();
+    assert_eq!(path.last(), Some(&ivec2(4, 2)));
+    assert_eq!(path.len(), 4);
+    assert_walkable(&world, ivec2(0, 2), &path, ivec2(5, 2));
+}
+
+#[test]
+fn paths_are_forgotten_when_machines_change_where_they_looked() {
+    let mut world = map(&[
+        ".....#....",
+        ".....#....",
+        ".....#..T.",
+        "..........",
+    ]);
+    let (from, to) = (ivec2(2, 3), ivec2(8, 2));
+    // Right under the wall
+    let short = world.find_path(from, to).unwrap();
+    assert_eq!(short.len(), 7);
+    assert!(world.pathfinder().is_cached(to));
+    // Far from anything the search looked at
+    world.set_tower(ivec2(60, 60), Tower::PowerNode.new_machine().unwrap());
+    assert!(world.pathfinder().is_cached(to));
+
+    // Blocking the way makes it go around again
+    world.set_tower(ivec2(5, 3), Tower::PowerNode.new_machine().unwrap());
+    assert!(!world.pathfinder().is_cached(to));
+    let longer = world.find_path(from, to).unwrap();
+    assert_walkable(&world, from, &longer, to);
+    assert_eq!(longer.len(), 9);
+
+    // Removing it opens the shortcut again
+    world.set_tower(ivec2(5, 3), Tower::Empty.new_machine().unwrap());
+    assert_eq!(world.find_path(from, to).unwrap(), short);
+    // The target going away is a change too
+    world.set_tower(to, Tower::Empty.new_machine().unwrap());
+    assert!(world.find_path(from, to).is_none());
+}
+
+#[test]
+fn only_the_last_used_targets_are_remembered() {
+    let mut world = World::new(1022);
+    let count = pathfinding::MAX_CACHED_TARGETS as i32+5;
+    for x in 0..count {
+        world.set_tower(ivec2(x*2, 0), Tower::PowerNode.new_machine().unwrap());
+    }
+    for x in 0..count {
+        assert!(world.find_path(ivec2(x*2, 3), ivec2(x*2, 0)).is_some());
+    }
+    assert_eq!(world.pathfinder().cached_len(), pathfinding::MAX_CACHED_TARGETS);
+    assert!(!world.pathfinder().is_cached(ivec2(0, 0)));
+    assert!(world.pathfinder().is_cached(ivec2((count-1)*2, 0)));
+}
+
+#[test]
+fn far_starts_have_no_path() {
+    let mut world = map(&["T"]);
+    // The search around the target stops after MAX_SEARCHED_CELLS cells, a diamond about 64 tiles across
+    assert!(world.find_path(ivec2(50, 0), ivec2(0, 0)).is_some());
+    assert!(world.find_path(ivec2(100, 0), ivec2(0, 0)).is_none());
+}
@@ -0,0 +1,91 @@
+use initerse::{clock::TICKS_PER_SECOND, item::Item, recipe::{self, parse_recipe_file}, tiles::{new_machine, World}, tower::{crafter::Crafter, Tower}, *};
+use strum::IntoEnumIterator;
+
+mod common;
+use common::*;
+
+/// A string reshaper making quarks out of 2 antimatter, next to vacuum collectors (it needs 2 of them)
+fn powered_reshaper(collectors: usize) -> World {
+    let mut world = World::new(1022);
+    let mut crafter = Crafter::new(Tower::StringReshaper);
+    crafter.select(recipe::find("Quarks").unwrap());
+    world.set_tower(ivec2(1, 0), new_machine(crafter));
+    for y in 0..collectors {
+        world.set_tower(ivec2(0, y as i32), Tower::VacuumCollector.new_machine().unwrap());
+    }
+    assert_eq!(world.try_get_tower_mut(&ivec2(1, 0)).unwrap().insert(Item::Antimatter, 2), 0);
+    world
+}
+fn error_of(raw: &str) -> String {
+    format!("{:?}", parse_recipe_file(raw).unwrap_err())
+}
+
+#[test]
+fn bundled_recipes_are_valid() {
+    let recipes = recipe::parse_recipes_in(recipe::RECIPES_DIR).unwrap();
+    assert!(!recipes.is_empty());
+    for tower in Tower::iter().filter(|tower| tower.is_crafter()) {
+        assert!(recipes.iter().any(|recipe| recipe.machine == tower), "Nothing to craft in {:?}", tower);
+    }
+}
+
+#[test]
+fn invalid_recipes_are_explained() {
+    let recipe = |body: &str| format!("[[recipe]]\nname = \"Broken\"\nmachine = \"StringReshaper\"\nduration = 1.0\n{body}");
+    assert!(error_of(&recipe("outputs = { Qark = 1 }")).contains("Unknown item \"Qark\""));
+    assert!(error_of(&recipe("outputs = { Quark = 0 }")).contains("positive integer"));
+    assert!(error_of(&recipe("inputs = { Quark = 1 }")).contains("outputs"));
+    assert!(error_of(&recipe("outputs = { Quark = 1 }\nenergy = -1.0")).contains("can't be negative"));
+    assert!(error_of(&recipe("outputs = { Quark = 1 }\ncolor = 3")).contains("color"));
+    let not_a_crafter = "[[recipe]]\nname = \"Broken\"\nmachine = \"Battery\"\nduration = 1.0\noutputs = { Quark = 1 }";
+    assert!(error_of(not_a_crafter).contains("Battery isn't a crafter"));
+    // Errors name the recipe at fault
+    assert!(error_of(&recipe("outputs = { Qark = 1 }")).contains("In recipe \"Broken\""));
+
+    let ok = parse_recipe_file(&recipe("outputs = { Quark = 1 }")).unwrap();
+    let twice: Vec<_> = ok.iter().chain(&ok).cloned().collect();
+    assert!(format!("{:?}", recipe::validate(&twice).unwrap_err()).contains("Two recipes are named \"Broken\""));
+}
+
+#[test]
+fn crafters_turn_inputs_into_outputs() {
+    let mut world = powered_reshaper(2);
+    // One tick to get power, then 2 seconds per craft
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2+1).unwrap();
+    assert_eq!(count(&world, ivec2(1, 0), Item::Quark), 3);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*4).unwrap();
+    // Out of antimatter after the second craft, idle crafters don't draw power
+    assert_eq!(count(&world, ivec2(1, 0), Item::Quark), 6);
+    world.tick(Vec2::ZERO, 1).unwrap();
+    assert_eq!(world.grid_stats(ivec2(1, 0)).unwrap().demand, 0.);
+}
+
+#[test]
+fn crafters_slow_down_without_enough_power() {
+    let mut world = powered_reshaper(1);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2+1).unwrap();
+    assert_eq!(count(&world, ivec2(1, 0), Item::Quark), 0);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2).unwrap();
+    assert_eq!(count(&world, ivec2(1, 0), Item::Quark), 3);
+}
+
+#[test]
+fn crafters_only_accept_their_inputs() {
+    let mut world = powered_reshaper(0);
+    let crafter = world.try_get_tower_mut(&ivec2(1, 0)).unwrap();
+    assert_eq!(crafter.insert(Item::Iron, 5), 5);
+    // A few crafts in advance, not more
+    assert_eq!(crafter.insert(Item::Antimatter, 100), 100-(5-2));
+    let mut idle = Tower::BaryonicCombinator.new_machine().unwrap();
+    assert_eq!(idle.insert(Item::Quark, 3), 3);
+}
+
+#[test]
+fn selected_recipe_is_saved() {
+    let mut world = powered_reshaper(1);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    let saved = loaded.get_tower(&ivec2(1, 0)).serialize().unwrap();
+    assert_eq!(saved, world.get_tower(&ivec2(1, 0)).serialize().unwrap());
+    assert_eq!(saved["recipe"].as_str(), Some("Quarks"));
+}
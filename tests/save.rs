@@ -0,0 +1,71 @@
+use initerse::{item::Item, save::{SaveFile, SAVE_VERSION}, tiles::World, tower::Tower, *};
+
+fn header(version: u32) -> String {
+    format!("version = {}\nseed = 3\nmeta = {{ name = \"Test\", created = 0, last_played = 0, play_time = 0.0 }}\n", version)
+}
+fn load_err(raw: &str) -> String {
+    match World::load(raw.to_string()) {
+        Ok(_) => panic!("Save should be rejected:\n{}", raw),
+        Err(e) => format!("{:#}", e),
+    }
+}
+
+#[test]
+fn saves_are_versioned_toml() {
+    let mut world = World::new(42);
+    world.set_tower(ivec2(2, 3), Tower::Electron.new_machine().unwrap());
+    let raw = world.serialize().unwrap();
+    let save: toml::Table = toml::from_str(&raw).unwrap();
+    assert_eq!(save["version"].as_integer(), Some(SAVE_VERSION as i64));
+    assert_eq!(save["seed"].as_integer(), Some(42));
+    assert_eq!(World::load(raw.clone()).unwrap().serialize().unwrap(), raw);
+}
+
+#[test]
+fn corrupt_machine_state_reports_its_line() {
+    let raw = header(SAVE_VERSION)+"\n[[machines]]\npos = [0, 0]\ntower = \"Electron\"\n\n[machines.state]\nprogress = \"lots\"\n";
+    let err = load_err(&raw);
+    assert!(err.contains("Electron at [0, 0] (line 5)"), "{}", err);
+}
+
+#[test]
+fn unknown_tower_is_an_error() {
+    let raw = header(SAVE_VERSION)+"\n[[machines]]\npos = [0, 0]\ntower = \"Teleporter\"\n";
+    let err = load_err(&raw);
+    assert!(err.contains("line 7"), "{}", err);
+}
+
+#[test]
+fn future_versions_are_rejected() {
+    let raw = header(SAVE_VERSION+1);
+    assert!(load_err(&raw).contains("Unsupported save version"));
+}
+
+#[test]
+fn legacy_saves_are_imported() {
+    let raw = std::fs::read_to_string("saves/1022").unwrap();
+    let save = SaveFile::parse(&raw).unwrap();
+    assert_eq!(save.version, SAVE_VERSION);
+    assert_eq!(save.seed, 1022);
+    assert_eq!(save.machines.len(), 19);
+    let world = World::load(raw).unwrap();
+    let electron = world.get_tower(&ivec2(8, 11));
+    assert_eq!(electron.inventory().unwrap().count(Item::String), 14);
+}
+
+#[test]
+fn corrupt_legacy_line_is_an_error() {
+    let raw = "Seed = 7\nWorld = [\n    [0, 0]: Electron {buffer: 1.5}\n    [1, 0]: Electron {buffer: many}\n]\n";
+    let err = load_err(raw);
+    assert!(err.contains("line 4"), "{}", err);
+}
+
+#[test]
+fn malformed_legacy_args_are_errors() {
+    // Too short, and a multibyte character where the value should start
+    for args in ["{buf}", "{buffer:é1}", "{}"] {
+        let raw = format!("Seed = 7\nWorld = [\n    [0, 0]: Electron {}\n]\n", args);
+        let err = load_err(&raw);
+        assert!(err.contains("line 3"), "{}", err);
+    }
+}
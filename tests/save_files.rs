@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use initerse::{save::{files::{Autosave, SaveDir}, meta}, tiles::World, tower::Tower, *};
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("initerse-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn saving_keeps_the_last_backups() {
+    let root = temp_dir("backups");
+    let dir = SaveDir::new(&root);
+    for i in 0..6 {
+        dir.write("world", &format!("save {}", i), 3).unwrap();
+    }
+    assert_eq!(std::fs::read_to_string(dir.save_path("world")).unwrap(), "save 5");
+    let backups = dir.backups("world");
+    assert_eq!(backups.len(), 3);
+    let contents: Vec<String> = backups.iter().map(|b| std::fs::read_to_string(b).unwrap()).collect();
+    assert_eq!(contents, ["save 4", "save 3", "save 2"]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn backups_can_be_disabled() {
+    let root = temp_dir("no-backups");
+    let dir = SaveDir::new(&root);
+    dir.write("world", "first", 0).unwrap();
+    dir.write("world", "second", 0).unwrap();
+    assert!(dir.backups("world").is_empty());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn listing_saves_skips_backups_and_unfinished_writes() {
+    let root = temp_dir("listing");
+    let dir = SaveDir::new(&root);
+    dir.write("a", "1", 2).unwrap();
+    dir.write("a", "2", 2).unwrap();
+    dir.write("b", "1", 2).unwrap();
+    std::fs::write(root.join("c.tmp"), "half a save").unwrap();
+    assert_eq!(dir.saves().unwrap(), [dir.save_path("a"), dir.save_path("b")]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn restoring_a_backup_brings_back_the_world() {
+    let root = temp_dir("restore");
+    let dir = SaveDir::new(&root);
+    let mut world = World::new(12);
+    world.set_tower(ivec2(0, 0), Tower::Electron.new_machine().unwrap());
+    world.save_to(&dir, 2).unwrap();
+    world.set_tower(ivec2(0, 0), Tower::Empty.new_machine().unwrap());
+    world.save_to(&dir, 2).unwrap();
+
+    let name = world.save_name().unwrap().to_string();
+    let latest = World::load(std::fs::read_to_string(dir.save_path(&name)).unwrap()).unwrap();
+    assert_eq!(latest.machine_count(), 0);
+    dir.restore_backup(&name, &dir.backups(&name)[0], 2).unwrap();
+    let restored = World::load(std::fs::read_to_string(dir.save_path(&name)).unwrap()).unwrap();
+    assert_eq!(restored.get_tower(&ivec2(0, 0)).ty(), Tower::Electron);
+
+    // The world from before restoring is now the latest backup, so it can be brought back too
+    let backups = dir.backups(&name);
+    assert_eq!(backups.len(), 2);
+    dir.restore_backup(&name, &backups[0], 2).unwrap();
+    let undone = World::load(std::fs::read_to_string(dir.save_path(&name)).unwrap()).unwrap();
+    assert_eq!(undone.machine_count(), 0);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn autosave_triggers_on_interval() {
+    let mut autosave = Autosave::new();
+    let saves = (0..100).filter(|_| autosave.update(0.5, 10.)).count();
+    assert_eq!(saves, 5);
+    assert!(!(0..100).any(|_| autosave.update(0.5, 0.)));
+}
+
+#[test]
+fn worlds_are_saved_under_their_name() {
+    let root = temp_dir("named");
+    let dir = SaveDir::new(&root);
+    let mut first = World::new(1);
+    first.meta_mut().name = "My Base".to_string();
+    first.save_to(&dir, 1).unwrap();
+    let mut second = World::new(1);
+    second.meta_mut().name = "My Base".to_string();
+    second.save_to(&dir, 1).unwrap();
+    assert_eq!(first.save_name(), Some("my_base"));
+    assert_eq!(second.save_name(), Some("my_base_2"));
+    assert_eq!(dir.load("my_base_2").unwrap().meta().name, "My Base");
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn worlds_can_be_renamed_duplicated_and_deleted() {
+    let root = temp_dir("manage");
+    let dir = SaveDir::new(&root);
+    let mut world = World::new(3);
+    world.meta_mut().name = "Alpha".to_string();
+    world.set_tower(ivec2(1, 2), Tower::Electron.new_machine().unwrap());
+    world.save_to(&dir, 2).unwrap();
+    world.save_to(&dir, 2).unwrap();
+
+    let renamed = dir.rename("alpha", "Beta").unwrap();
+    assert_eq!(renamed, "beta");
+    assert!(!dir.exists("alpha"));
+    assert_eq!(dir.backups("beta").len(), 1);
+    assert_eq!(dir.read_meta("beta").unwrap().name, "Beta");
+
+    let copy = dir.duplicate("beta").unwrap();
+    let copied = dir.load(&copy).unwrap();
+    assert_eq!(copied.meta().name, "Beta (copy)");
+    assert_eq!(copied.get_tower(&ivec2(1, 2)).ty(), Tower::Electron);
+
+    dir.delete("beta").unwrap();
+    assert!(!dir.exists("beta"));
+    assert!(dir.backups("beta").is_empty());
+    assert_eq!(dir.saves().unwrap(), [dir.save_path(&copy)]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn saving_records_metadata() {
+    let root = temp_dir("meta");
+    let dir = SaveDir::new(&root);
+    let mut world = World::new(8);
+    world.meta_mut().play_time = 125.;
+    world.set_tower(ivec2(0, 0), Tower::Electron.new_machine().unwrap());
+    world.set_tower(ivec2(5, 5), Tower::AntimatterCollector.new_machine().unwrap());
+    world.save_to(&dir, 0).unwrap();
+    let meta = dir.read_meta(world.save_name().unwrap()).unwrap();
+    assert!(meta.last_played >= meta.created);
+    assert_eq!(meta::format_duration(meta.play_time), "0h 02m");
+    let thumbnail = meta.thumbnail.unwrap().rgba();
+    assert_eq!(&thumbnail[..4], &[0x3f, 0xa9, 0xf5, 255]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn dates_are_formatted() {
+    assert_eq!(meta::format_date(0), "1970-01-01 00:00");
+    assert_eq!(meta::format_date(1_700_000_000), "2023-11-14 22:13");
+}
@@ -0,0 +1,66 @@
+use initerse::{tiles::World, *};
+
+fn layout(world: &World) -> Vec<(IVec2, IVec2)> {
+    world.celestials().iter().map(|(coords, celest)| (*coords, celest.size)).collect()
+}
+
+#[test]
+fn seed_hash_is_stable() {
+    // Changing this breaks every world shared by its seed
+    assert_eq!(hash_seed(""), 0xcbf29ce484222325);
+    assert_eq!(hash_seed("initerse"), hash_seed("initerse"));
+    assert_eq!(hash_seed("a"), 0xaf63dc4c8601ec8c);
+}
+
+#[test]
+fn seed_order_matters() {
+    assert_ne!(hash_seed("ab"), hash_seed("ba"));
+    assert_ne!(hash_seed("1022"), hash_seed("2201"));
+}
+
+#[test]
+fn same_seed_gives_same_layout() {
+    let seed = hash_seed("my world");
+    let first = World::new(seed);
+    // Generation must not depend on the global generator
+    rand::srand(42);
+    let _ = rand::gen_range(0, 100);
+    let second = World::new(seed);
+    assert!(!first.celestials().is_empty());
+    assert_eq!(layout(&first), layout(&second));
+}
+
+#[test]
+fn different_seeds_give_different_layouts() {
+    let layouts: Vec<_> = ["a", "b", "c", "d"].iter().map(|s| layout(&World::new(hash_seed(s)))).collect();
+    assert!(layouts.windows(2).any(|w| w[0] != w[1]));
+}
+
+#[test]
+fn loaded_worlds_keep_their_layout() {
+    let mut world = World::new(hash_seed("reload"));
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(layout(&world), layout(&loaded));
+}
+
+#[test]
+fn world_rng_is_deterministic() {
+    let mut a = WorldRng::new(7);
+    let mut b = WorldRng::new(7);
+    for _ in 0..100 {
+        let n = a.gen_range(-3., 5.);
+        assert_eq!(n, b.gen_range(-3., 5.));
+        assert!((-3. ..5.).contains(&n));
+    }
+}
+
+#[test]
+fn empty_int_ranges_give_their_start() {
+    let mut rng = WorldRng::new(7);
+    assert_eq!(rng.gen_range_i32(4, 4), 4);
+    assert_eq!(rng.gen_range_i32(4, -2), 4);
+    for _ in 0..100 {
+        assert!((-3..5).contains(&rng.gen_range_i32(-3, 5)));
+    }
+    assert!((i32::MIN..i32::MAX).contains(&rng.gen_range_i32(i32::MIN, i32::MAX)));
+}
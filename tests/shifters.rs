@@ -0,0 +1,136 @@
+use initerse::{clock::TICKS_PER_SECOND, enemy::{self, Difficulty, Shifter, ANNIHILATION_RADIUS}, entity::EntityType, tiles::World, tower::Tower, *};
+
+fn shifters(world: &World) -> Vec<Vec2> {
+    world.entities().of_type(EntityType::Shifter).map(|entity| entity.body.pos).collect()
+}
+fn place(world: &mut World, cells: &[(i32, i32)]) {
+    for (x, y) in cells {
+        world.set_tower(ivec2(*x, *y), Tower::PowerNode.new_machine().unwrap());
+    }
+}
+
+#[test]
+fn peaceful_worlds_have_no_waves() {
+    let mut world = World::new(1022);
+    place(&mut world, &[(0, 0)]);
+    assert_eq!(world.waves().difficulty, Difficulty::Peaceful);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*600).unwrap();
+    assert!(shifters(&world).is_empty());
+    assert_eq!(Difficulty::Peaceful.next(), Difficulty::Easy);
+    assert_eq!(Difficulty::Hard.next(), Difficulty::Peaceful);
+}
+
+#[test]
+fn waves_grow_with_energy_production() {
+    let normal = Difficulty::Normal;
+    assert_eq!(normal.wave_interval(0.), Some(180.));
+    assert_eq!(normal.wave_interval(50.), Some(90.));
+    assert_eq!(normal.wave_interval(1e6), Some(enemy::MIN_WAVE_INTERVAL));
+    assert_eq!(normal.wave_size(0.), 2);
+    assert_eq!(normal.wave_size(120.), 4);
+    assert_eq!(normal.wave_size(1e6), enemy::MAX_WAVE_SIZE);
+    assert_eq!(Difficulty::Peaceful.wave_interval(100.), None);
+}
+
+#[test]
+fn waves_come_from_the_edge_of_the_base() {
+    let mut world = World::new(1022);
+    world.set_difficulty(Difficulty::Hard);
+    place(&mut world, &[(0, 0), (10, 0)]);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*89).unwrap();
+    assert!(shifters(&world).is_empty());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    let wave = shifters(&world);
+    assert_eq!(wave.len(), 4);
+    assert_eq!(world.waves().count, 1);
+    // Just past the furthest machine, the wave spreads a bit around where it comes from
+    for pos in wave {
+        assert!((27.5..34.).contains(&pos.length()), "{pos}");
+    }
+    // Vacuum collectors make 5 energy per second, the next wave comes sooner
+    world.set_tower(ivec2(0, 1), Tower::VacuumCollector.new_machine().unwrap());
+    world.tick(Vec2::ZERO, 2).unwrap();
+    assert!(world.waves().until_next < 90./1.1, "{}", world.waves().until_next);
+}
+
+#[test]
+fn shifters_go_for_the_nearest_structure() {
+    let mut world = World::new(1022);
+    place(&mut world, &[(10, 0), (-10, 0)]);
+    world.entities_mut().spawn(vec2(3., 0.5), Box::new(Shifter::new()));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*2).unwrap();
+    assert!(shifters(&world)[0].x > 5.5, "{:?}", shifters(&world));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+    assert!(shifters(&world).is_empty());
+    assert!(world.try_get_tower(&ivec2(10, 0)).is_none());
+    assert!(world.try_get_tower(&ivec2(-10, 0)).is_some());
+}
+
+#[test]
+fn shifters_too_far_for_a_path_fly_straight() {
+    let mut world = World::new(1022);
+    place(&mut world, &[(0, 0)]);
+    world.entities_mut().spawn(vec2(100.5, 0.5), Box::new(Shifter::new()));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*4).unwrap();
+    assert!(shifters(&world)[0].x < 95., "{:?}", shifters(&world));
+    assert!((shifters(&world)[0].y-0.5).abs() < 0.01, "{:?}", shifters(&world));
+}
+
+#[test]
+fn shifters_keep_moving_while_offline() {
+    const NOW: u64 = 1_700_000_000;
+    let mut world = World::new(1022);
+    place(&mut world, &[(0, 0)]);
+    world.entities_mut().spawn(vec2(12.5, 0.5), Box::new(Shifter::new()));
+    world.meta_mut().last_played = NOW-20;
+    world.offline_progress(NOW, 3600.).unwrap();
+    // 12 cells at 1.5 tiles/s, not one cell per offline step
+    assert!(shifters(&world).is_empty(), "{:?}", shifters(&world));
+    assert_eq!(world.machine_count(), 0);
+}
+
+#[test]
+fn shifters_annihilate_with_everything_around() {
+    let mut world = World::new(1022);
+    // A line of machines, the shifter comes from above
+    place(&mut world, &[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)]);
+    world.set_tower(ivec2(0, 1), Tower::Battery.new_machine().unwrap());
+    world.entities_mut().spawn(vec2(2., -3.), Box::new(Shifter::new()));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*3).unwrap();
+    assert!(shifters(&world).is_empty());
+    let left: Vec<i32> = (0..6).filter(|x| world.try_get_tower(&ivec2(*x, 0)).is_some()).collect();
+    assert_eq!(left, [4, 5], "{ANNIHILATION_RADIUS} tiles around where it hit, a bit left of (2, 0)");
+    // Touched by the blast on its corner
+    assert!(world.try_get_tower(&ivec2(1, 2)).is_none());
+}
+
+#[test]
+fn waves_and_shifters_are_saved() {
+    let mut world = World::new(1022);
+    world.set_difficulty(Difficulty::Easy);
+    place(&mut world, &[(0, 0)]);
+    world.entities_mut().spawn(vec2(30., 0.5), Box::new(Shifter::new()));
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND).unwrap();
+    let raw = world.serialize().unwrap();
+    let mut loaded = World::load(raw).unwrap();
+    assert_eq!(loaded.waves(), world.waves());
+    assert_eq!(shifters(&loaded), shifters(&world));
+    loaded.tick(Vec2::ZERO, TICKS_PER_SECOND*30).unwrap();
+    assert!(shifters(&loaded).is_empty());
+    assert_eq!(loaded.machine_count(), 0);
+}
+
+#[test]
+fn shifters_share_the_search_of_their_target() {
+    let mut world = World::new(1022);
+    place(&mut world, &[(0, 0)]);
+    for pos in [vec2(12.5, 0.5), vec2(-12.5, 3.5), vec2(0.5, -12.5), vec2(4.5, 11.5)] {
+        world.entities_mut().spawn(pos, Box::new(Shifter::new()));
+    }
+    // Every shifter looks for a path again each second, from wherever it is by then
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*4).unwrap();
+    assert_eq!(shifters(&world).len(), 4);
+    assert!(shifters(&world).iter().all(|pos| pos.length() < 10.), "{:?}", shifters(&world));
+    assert_eq!(world.pathfinder().cached_len(), 1);
+    assert!(world.pathfinder().is_cached(ivec2(0, 0)));
+}
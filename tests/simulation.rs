@@ -0,0 +1,58 @@
+use initerse::{chunk::CHUNK_SIZE, clock::TICKS_PER_SECOND, simulation::SimulationMode, tiles::World, tower::Tower, *};
+
+mod common;
+use common::*;
+
+/// An electron in each of `chunks` chunks, far from the player
+fn far_factory(chunks: i32) -> (World, Vec<IVec2>) {
+    let mut world = World::new(1022);
+    let coords: Vec<_> = (0..chunks).map(|i| ivec2(CHUNK_SIZE*(10+i), CHUNK_SIZE*10)).collect();
+    for c in &coords {
+        world.set_tower(*c, Tower::Electron.new_machine().unwrap());
+    }
+    (world, coords)
+}
+
+#[test]
+fn nearby_mode_freezes_far_machines() {
+    let (mut world, coords) = far_factory(1);
+    world.simulation_mut().mode = SimulationMode::Nearby;
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*3+1).unwrap();
+    assert_eq!(strings(&world, coords[0]), 0);
+}
+
+#[test]
+fn factory_mode_runs_far_machines_every_tick_within_budget() {
+    let (mut world, coords) = far_factory(3);
+    world.simulation_mut().mode = SimulationMode::Factory;
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*3+1).unwrap();
+    for c in coords {
+        assert_eq!(strings(&world, c), 3);
+        assert_eq!(world.simulation().behind(chunk::chunk_of(c)), 0.);
+    }
+}
+
+#[test]
+fn over_budget_chunks_are_fast_forwarded_in_turn() {
+    let (mut world, coords) = far_factory(4);
+    world.simulation_mut().mode = SimulationMode::Factory;
+    world.simulation_mut().budget = 0;
+    // A single chunk is fast-forwarded each tick, so each one waits 4 ticks
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*3+4).unwrap();
+    for c in coords {
+        assert_eq!(strings(&world, c), 3);
+        assert!(world.simulation().behind(chunk::chunk_of(c)) < 4.5*clock::TICK_DT);
+    }
+}
+
+#[test]
+fn fast_forward_matches_ticking() {
+    let (ticked, fast) = (ivec2(0, 0), ivec2(CHUNK_SIZE*50, 0));
+    let mut world = World::new(1022);
+    world.simulation_mut().budget = 0;
+    world.set_tower(ticked, Tower::Electron.new_machine().unwrap());
+    world.set_tower(fast, Tower::Electron.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*20+1).unwrap();
+    assert_eq!(strings(&world, ticked), 20);
+    assert_eq!(strings(&world, fast), 20);
+}
@@ -0,0 +1,101 @@
+use initerse::{clock::TICKS_PER_SECOND, item::{Inventory, Item}, tiles::{new_machine, World}, tower::{Machine, item_deposit::{ItemDeposit, ITEM_DEPOSIT_CAPACITY}, unsorted_deposit::{UnsortedDeposit, UNSORTED_DEPOSIT_SLOTS}, Tower, MAX_STORAGE_LEVEL}, *};
+
+mod common;
+use common::*;
+
+/// `from` then a pipe then `to`, from left to right, with 300 strings in `from`
+fn piped(from: Tower, to: Tower) -> World {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), from.new_machine().unwrap());
+    world.set_tower(ivec2(1, 0), Tower::ItemPipe.new_machine().unwrap());
+    world.set_tower(ivec2(2, 0), to.new_machine().unwrap());
+    assert_eq!(world.try_get_tower_mut(&ivec2(0, 0)).unwrap().insert(Item::String, 300), 0);
+    world
+}
+fn wallet(strings: u32) -> Inventory {
+    let mut inventory = Inventory::new(4);
+    inventory.insert(Item::String, strings);
+    inventory
+}
+
+#[test]
+fn unsorted_deposit_is_slow_to_empty() {
+    let mut world = piped(Tower::UnsortedDeposit, Tower::ItemDeposit);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    let moved = count(&world, ivec2(2, 0), Item::String);
+    assert!((9..=10).contains(&moved), "{moved}");
+}
+
+#[test]
+fn item_deposit_is_fast_both_ways() {
+    let mut world = piped(Tower::ItemDeposit, Tower::UnsortedDeposit);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    // Only the pipe holds it back
+    let moved = count(&world, ivec2(2, 0), Item::String);
+    assert!((70..=80).contains(&moved), "{moved}");
+}
+
+#[test]
+fn item_deposit_holds_a_single_type() {
+    let mut deposit = Tower::ItemDeposit.new_machine().unwrap();
+    assert_eq!(deposit.insert(Item::Iron, ITEM_DEPOSIT_CAPACITY+20), 20);
+    assert_eq!(deposit.space_for(Item::Iron), 0);
+    assert_eq!(deposit.take(Item::Iron, 100), 100);
+    assert_eq!(deposit.space_for(Item::Sulfur), 0);
+    assert_eq!(deposit.insert(Item::Sulfur, 1), 1);
+    // Takes anything again once empty
+    deposit.take(Item::Iron, u32::MAX);
+    assert_eq!(deposit.insert(Item::Sulfur, 1), 0);
+}
+
+#[test]
+fn upgrades_cost_strings_and_add_capacity() {
+    let mut deposit = ItemDeposit::new();
+    assert!(!deposit.try_upgrade(&mut wallet(10)));
+    let mut payer = wallet(400);
+    for _ in 0..MAX_STORAGE_LEVEL {
+        assert!(deposit.try_upgrade(&mut payer));
+    }
+    assert!(!deposit.try_upgrade(&mut payer));
+    // 50 + 100 + 150
+    assert_eq!(payer.count(Item::String), 100);
+    let mut deposit = new_machine(deposit);
+    assert_eq!(deposit.insert(Item::Iron, u32::MAX), u32::MAX-4*ITEM_DEPOSIT_CAPACITY);
+
+    let mut unsorted = UnsortedDeposit::new();
+    assert!(unsorted.try_upgrade(&mut wallet(50)));
+    assert_eq!(unsorted.inventory().unwrap().capacity(), UNSORTED_DEPOSIT_SLOTS+10);
+}
+
+#[test]
+fn upgrades_are_saved() {
+    let mut world = World::new(1022);
+    let mut deposit = ItemDeposit::new();
+    deposit.try_upgrade(&mut wallet(50));
+    world.set_tower(ivec2(0, 0), new_machine(deposit));
+    let mut unsorted = UnsortedDeposit::new();
+    unsorted.try_upgrade(&mut wallet(50));
+    world.set_tower(ivec2(1, 0), new_machine(unsorted));
+    let mut loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.try_get_tower_mut(&ivec2(0, 0)).unwrap().insert(Item::Iron, u32::MAX), u32::MAX-2*ITEM_DEPOSIT_CAPACITY);
+    assert_eq!(loaded.get_tower(&ivec2(1, 0)).inventory().unwrap().capacity(), UNSORTED_DEPOSIT_SLOTS+10);
+}
+
+#[test]
+fn overfull_stacks_have_no_space_left() {
+    let mut inventory = Inventory::new(2);
+    inventory.insert(Item::String, 10);
+    // A lower limit than what is already there, like after a stack size change
+    let inventory = inventory.with_stack_limit(4);
+    assert_eq!(inventory.space_for(Item::String), 4);
+}
+
+#[test]
+fn missing_items_dont_slow_the_unsorted_deposit() {
+    let mut deposit = UnsortedDeposit::new();
+    deposit.insert(Item::String, 5);
+    assert_eq!(deposit.take(Item::Photon, 1), 0);
+    assert_eq!(deposit.take(Item::String, 1), 1);
+    // Real withdrawals still wait
+    assert_eq!(deposit.take(Item::String, 1), 0);
+}
@@ -0,0 +1,78 @@
+use initerse::{clock::TICKS_PER_SECOND, tiles::World, tower::{string_creator::{STRING_CREATOR_BUFFER, StringCreator}, Tower}, *};
+
+mod common;
+use common::*;
+
+const CREATOR: IVec2 = ivec2(0, 0);
+
+/// A string creator at the origin with vacuum collectors (5 energy/s each) below it
+fn powered_creator(collectors: i32) -> World {
+    let mut towers = vec![(CREATOR, Tower::StringCreator)];
+    towers.extend((1..=collectors).map(|y| (ivec2(0, y), Tower::VacuumCollector)));
+    world_with(&towers)
+}
+fn buffer(world: &World) -> f32 {
+    let state = world.get_tower(&CREATOR).serialize().unwrap();
+    StringCreator::deserialize(state).unwrap().buffer()
+}
+
+#[test]
+fn string_creator_turns_energy_into_strings() {
+    let mut world = powered_creator(2);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    assert!((19..=20).contains(&strings(&world, CREATOR)), "{}", strings(&world, CREATOR));
+    // What the strings don't use goes to the buffer, minus what the last tick spent
+    assert!(buffer(&world) > STRING_CREATOR_BUFFER-1.);
+}
+
+#[test]
+fn string_creator_slows_down_without_enough_energy() {
+    let mut world = powered_creator(1);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*12).unwrap();
+    // 5 energy per second, 3 per string
+    assert!((19..=20).contains(&strings(&world, CREATOR)), "{}", strings(&world, CREATOR));
+    assert!(buffer(&world) < 1.);
+    let mut unpowered = powered_creator(0);
+    unpowered.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+    assert_eq!(strings(&unpowered, CREATOR), 0);
+}
+
+#[test]
+fn string_creator_buffer_covers_outages() {
+    let mut world = powered_creator(2);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    let before = strings(&world, CREATOR);
+    world.set_tower(ivec2(0, 1), Tower::Empty.new_machine().unwrap());
+    world.set_tower(ivec2(0, 2), Tower::Empty.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    // 30 energy is 10 strings
+    assert!((9..=10).contains(&(strings(&world, CREATOR)-before)), "{}", strings(&world, CREATOR)-before);
+    assert!(buffer(&world) < 0.01);
+}
+
+#[test]
+fn string_creator_state_is_saved() {
+    let mut world = powered_creator(1);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*3+7).unwrap();
+    let mut loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.get_tower(&ivec2(0, 0)).serialize().unwrap(), world.get_tower(&ivec2(0, 0)).serialize().unwrap());
+    // Keeps going the same way after loading
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+    loaded.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+    assert_eq!(strings(&loaded, CREATOR), strings(&world, CREATOR));
+}
+
+#[test]
+fn string_creator_catches_up_like_it_runs_live() {
+    const NOW: u64 = 1_000_000;
+    for collectors in [1, 2] {
+        let mut live = powered_creator(collectors);
+        live.tick(Vec2::ZERO, TICKS_PER_SECOND*100).unwrap();
+        let mut away = powered_creator(collectors);
+        away.meta_mut().last_played = NOW-100;
+        away.offline_progress(NOW, 3600.).unwrap();
+        // Long steps don't lose the energy that went past the buffer
+        let (live, away) = (strings(&live, CREATOR), strings(&away, CREATOR));
+        assert!(live.abs_diff(away) <= 3, "{} live, {} offline", live, away);
+    }
+}
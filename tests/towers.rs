@@ -0,0 +1,78 @@
+use initerse::{clock::TICKS_PER_SECOND, item::Item, tiles::World, tower::{registry::{self, parse_tower_file, Behaviour}, Tower}, *};
+
+fn matter_collector() -> Tower {
+    Tower::named("Matter collector").unwrap()
+}
+fn error_of(raw: &str) -> String {
+    format!("{:?}", parse_tower_file(raw).unwrap_err())
+}
+
+#[test]
+fn bundled_towers_are_registered() {
+    let defs = registry::parse_towers_in(registry::TOWERS_DIR).unwrap();
+    assert_eq!(defs.len(), registry::definitions().len());
+    let tower = matter_collector();
+    assert!(matches!(tower, Tower::Defined(_)));
+    assert_eq!(tower.name(), "Matter collector");
+    assert_eq!(tower.size(), 2);
+    assert!(registry::buildable().any(|t| t == tower));
+    // The towers written in Rust are still there
+    assert_eq!(Tower::named("Electron"), Some(Tower::Electron));
+    assert!(registry::buildable().any(|t| t == Tower::Electron));
+}
+
+#[test]
+fn invalid_towers_are_explained() {
+    let tower = |name: &str, body: &str| format!("name = \"{name}\"\ntexture = \"x.png\"\n{body}\n[behaviour]\nkind = \"storage\"");
+    assert!(error_of(&tower("Electron", "")).contains("already has this name"));
+    assert!(error_of(&tower("Huge", "size = 9")).contains("size should be between"));
+    assert!(error_of(&tower("Sideways", "inputs = \"up\"")).contains("Unknown side \"up\""));
+    assert!(error_of(&tower("Empty box", "inventory = 0")).contains("at least one slot"));
+    assert!(error_of(&tower("Colorful", "colour = \"fff\"")).contains("colour"));
+    assert!(error_of(&tower("Huge", "size = 9")).contains("In tower \"Huge\""));
+    let collector = "name = \"Slow\"\ntexture = \"x.png\"\n[behaviour]\nkind = \"collector\"\nitem = \"Quark\"\nrate = 0.0";
+    assert!(error_of(collector).contains("rate should be positive"));
+    assert!(error_of("name = \"Odd\"\ntexture = \"x.png\"\n[behaviour]\nkind = \"teleporter\"").contains("teleporter"));
+
+    let def = parse_tower_file(&tower("Crate", "inventory = 3")).unwrap();
+    assert_eq!(def.behaviour, Behaviour::Storage);
+    assert!(format!("{:?}", registry::validate(&[def.clone(), def]).unwrap_err()).contains("Two towers are named \"Crate\""));
+}
+
+#[test]
+fn declared_collectors_run_on_power() {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), matter_collector().new_machine().unwrap());
+    world.set_tower(ivec2(2, 0), Tower::VacuumCollector.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10+1).unwrap();
+    // Only 5 of the 8 energy per second it needs
+    assert_eq!(world.get_tower(&ivec2(1, 1)).inventory().unwrap().count(Item::Antimatter), 0);
+    world.set_tower(ivec2(2, 1), Tower::VacuumCollector.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10+1).unwrap();
+    assert_eq!(world.get_tower(&ivec2(1, 1)).inventory().unwrap().count(Item::Antimatter), 1);
+}
+
+#[test]
+fn declared_storage_holds_items() {
+    let mut storage = Tower::named("Storage crate").unwrap().new_machine().unwrap();
+    assert_eq!(storage.insert(Item::Iron, 30), 0);
+    // 4 slots, the other 3 are still free
+    assert_eq!(storage.space_for(Item::Sulfur), 3*Item::Sulfur.stack_size());
+    assert_eq!(storage.take(Item::Iron, 10), 10);
+}
+
+#[test]
+fn declared_towers_are_saved_by_name() {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(4, 4), matter_collector().new_machine().unwrap());
+    world.try_get_tower_mut(&ivec2(4, 4)).unwrap().insert(Item::Antimatter, 2);
+    let raw = world.serialize().unwrap();
+    assert!(raw.contains("tower = \"Matter collector\""), "{raw}");
+    let loaded = World::load(raw.clone()).unwrap();
+    assert_eq!(loaded.get_tower(&ivec2(5, 5)).ty(), matter_collector());
+    assert_eq!(loaded.get_tower(&ivec2(4, 4)).inventory().unwrap().count(Item::Antimatter), 2);
+
+    let unknown = raw.replace("Matter collector", "Dark matter collector");
+    let err = format!("{:?}", World::load(unknown).err().unwrap());
+    assert!(err.contains("unknown tower \"Dark matter collector\""), "{err}");
+}
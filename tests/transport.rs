@@ -0,0 +1,130 @@
+use initerse::{chunk::CHUNK_SIZE, clock::TICKS_PER_SECOND, direction::{Direction, Side}, tiles::World, tower::{transport, Tower}, *};
+
+mod common;
+use common::*;
+
+/// `length` transports of the given type after `source`, then a deposit, from left to right
+fn chain(world: &mut World, source: IVec2, transport: Tower, length: i32) -> IVec2 {
+    for x in 1..=length {
+        world.set_tower(source+ivec2(x, 0), transport.new_machine().unwrap());
+    }
+    let deposit = source+ivec2(length+1, 0);
+    world.set_tower(deposit, Tower::UnsortedDeposit.new_machine().unwrap());
+    deposit
+}
+/// An Electron that already made `seconds` worth of strings
+fn stocked_electron(world: &mut World, coords: IVec2, seconds: u32) {
+    world.set_tower(coords, Tower::Electron.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*seconds+1).unwrap();
+}
+
+#[test]
+fn conveyors_carry_electron_output_to_storage() {
+    let mut world = World::new(1022);
+    world.set_tower(ivec2(0, 0), Tower::Electron.new_machine().unwrap());
+    let deposit = chain(&mut world, ivec2(0, 0), Tower::Conveyor, 3);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*20).unwrap();
+    // A string per second, each one takes a bit less than 2 seconds to get to the end
+    let delivered = strings(&world, deposit);
+    assert!((18..=19).contains(&delivered), "{delivered}");
+    assert_eq!(strings(&world, ivec2(0, 0)), 0);
+}
+
+#[test]
+fn transports_move_items_at_their_throughput() {
+    for transport in [Tower::Conveyor, Tower::ItemPipe] {
+        let mut world = World::new(1022);
+        // The Electron isn't the bottleneck with a stock
+        stocked_electron(&mut world, ivec2(0, 0), 400);
+        let deposit = chain(&mut world, ivec2(0, 0), transport, 5);
+        // Once the chain is full
+        world.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+        let before = strings(&world, deposit);
+        world.tick(Vec2::ZERO, TICKS_PER_SECOND*20).unwrap();
+        let rate = (strings(&world, deposit)-before) as f32/20.;
+        let expected = transport::throughput(transport).unwrap();
+        assert!(rate > expected*0.9 && rate <= expected, "{transport:?}: {rate} items/s, expected {expected}");
+    }
+}
+
+#[test]
+fn pipes_dont_pick_up_from_the_front() {
+    let mut world = World::new(1022);
+    stocked_electron(&mut world, ivec2(2, 0), 10);
+    // Facing right, away from the deposit on the left
+    world.set_tower(ivec2(1, 0), Tower::ItemPipe.new_machine().unwrap());
+    world.set_tower(ivec2(0, 0), Tower::UnsortedDeposit.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+    assert_eq!(strings(&world, ivec2(0, 0)), 0);
+}
+
+#[test]
+fn items_in_transit_are_saved() {
+    let mut world = World::new(1022);
+    stocked_electron(&mut world, ivec2(0, 0), 20);
+    chain(&mut world, ivec2(0, 0), Tower::Conveyor, 4);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND/2).unwrap();
+    let in_transit = |world: &World| -> Vec<(IVec2, usize)> {
+        (1..=4).map(|x| ivec2(x, 0)).map(|c| (c, world.get_tower(&c).serialize().unwrap()["items"].as_array().unwrap().len())).collect()
+    };
+    assert!(in_transit(&world).iter().any(|(_, n)| *n > 0));
+    let loaded = World::load(world.serialize().unwrap()).unwrap();
+    assert_eq!(in_transit(&loaded), in_transit(&world));
+}
+
+#[test]
+fn distant_chains_are_fast_forwarded_at_their_throughput() {
+    let source = ivec2(CHUNK_SIZE*20, 0);
+    let mut world = World::new(1022);
+    world.simulation_mut().budget = 0;
+    stocked_electron(&mut world, source, 400);
+    let deposit = chain(&mut world, source, Tower::Conveyor, 3);
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*10).unwrap();
+    let delivered = strings(&world, deposit);
+    let expected = transport::throughput(Tower::Conveyor).unwrap()*10.;
+    assert!(delivered as f32 > expected*0.9 && delivered as f32 <= expected, "{delivered}");
+}
+
+#[test]
+fn rotated_chains_follow_their_facing() {
+    let mut world = World::new(1022);
+    stocked_electron(&mut world, ivec2(0, 0), 10);
+    for y in 1..=3 {
+        world.set_tower(ivec2(0, y), Tower::Conveyor.new_machine().unwrap());
+        world.set_facing(&ivec2(0, y), Direction::Down);
+    }
+    world.set_tower(ivec2(0, 4), Tower::UnsortedDeposit.new_machine().unwrap());
+    // Right of the first conveyor, it shouldn't get anything
+    world.set_tower(ivec2(1, 1), Tower::UnsortedDeposit.new_machine().unwrap());
+    world.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+    assert!(strings(&world, ivec2(0, 4)) >= 10);
+    assert_eq!(strings(&world, ivec2(1, 1)), 0);
+}
+
+#[test]
+fn only_conveyors_accept_items_from_the_side() {
+    for (transport, accepts) in [(Tower::Conveyor, true), (Tower::ItemPipe, false)] {
+        let mut world = World::new(1022);
+        stocked_electron(&mut world, ivec2(0, 0), 10);
+        // Goes right then down (a conveyor so it takes the turn), into the side of the last transport which goes right
+        world.set_tower(ivec2(1, 0), Tower::ItemPipe.new_machine().unwrap());
+        world.set_tower(ivec2(2, 0), Tower::Conveyor.new_machine().unwrap());
+        world.set_facing(&ivec2(2, 0), Direction::Down);
+        world.set_tower(ivec2(2, 1), transport.new_machine().unwrap());
+        let deposit = ivec2(3, 1);
+        world.set_tower(deposit, Tower::UnsortedDeposit.new_machine().unwrap());
+        world.tick(Vec2::ZERO, TICKS_PER_SECOND*5).unwrap();
+        assert_eq!(strings(&world, deposit) > 0, accepts, "{transport:?}");
+    }
+}
+
+#[test]
+fn sides_turn_with_the_facing() {
+    assert_eq!(Direction::Up.turned(Side::Right), Direction::Right);
+    assert_eq!(Direction::Left.turned(Side::Back), Direction::Right);
+    assert_eq!(Direction::Down.turned(Side::Left), Direction::Right);
+    assert_eq!(Direction::Down.side_of(Direction::Up), Side::Back);
+    assert!(Tower::Conveyor.accepts_from(Direction::Up, Direction::Left));
+    assert!(!Tower::ItemPipe.accepts_from(Direction::Up, Direction::Left));
+    assert!(Tower::ItemPipe.outputs_to(Direction::Up, Direction::Up));
+}